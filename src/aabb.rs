@@ -1,6 +1,10 @@
 use crate::vec3::{Vec3,Point3};
 use crate::interval::*;
 use crate::ray::Ray;
+use crate::util::INFINITY;
+
+// 1 + 2γ₃，γₙ = nε/(1-nε)，ε为单位舍入
+pub const ROBUST_FACTOR: f64 = 1.0 + 2.0 * (3.0 * f64::EPSILON * 0.5) / (1.0 - 3.0 * f64::EPSILON * 0.5);
 #[derive(Clone,Default)]
 pub struct Aabb {
     pub x: Interval,
@@ -34,17 +38,19 @@ impl Aabb {
         }
     }
 
+    // 稳健的slab测试（Ize 2013）：远端距离乘以 1+2γ₃ 抵消浮点舍入，避免漏掉掠射的光线；
+    // 光线平行于某轴且起点在边界上时得到NaN，比较为假，该轴不收窄区间；
+    // 方向分量为-0时倒数为-∞，按倒数的正负选近端与符号位一致
     pub fn hit(&self, r: &Ray, ray_t: &mut Interval) -> bool {
+        let inv_dir = r.inv_dir();
         for a in 0..3 {
-            let inv0 = 1.0 / r.dir[a];
+            let axis = self.axis(a);
+            let inv = inv_dir[a];
+            let (near, far) = if inv >= 0.0 { (axis.min, axis.max) } else { (axis.max, axis.min) };
             let orig = r.orig[a];
 
-            let mut t0 = (self.axis(a).min - orig) * inv0;
-            let mut t1 = (self.axis(a).max - orig) * inv0;
-
-            if inv0 < 0.0 {
-                std::mem::swap(&mut t0, &mut t1);
-            }
+            let t0 = (near - orig) * inv;
+            let t1 = (far - orig) * inv * ROBUST_FACTOR;
 
             if t0 > ray_t.min {
                 ray_t.min = t0;
@@ -53,7 +59,7 @@ impl Aabb {
                 ray_t.max = t1;
             }
 
-            if ray_t.max <= ray_t.min {
+            if ray_t.min > ray_t.max {
                 return false;
             }
         }
@@ -64,13 +70,13 @@ impl Aabb {
         //每个维度的大小至少为delta
         let delta = 0.0001;
         if self.x.size() < delta {
-            self.x.expand(delta);
+            self.x = self.x.expand(delta);
         }
         if self.y.size() < delta {
-            self.y.expand(delta);
+            self.y = self.y.expand(delta);
         }
         if self.z.size() < delta {
-            self.z.expand(delta);
+            self.z = self.z.expand(delta);
         }
     }
 
@@ -82,6 +88,30 @@ impl Aabb {
         }
     }
 
+    // 任一轴延伸到无穷远即为无界
+    pub fn is_bounded(&self) -> bool {
+        [&self.x, &self.y, &self.z]
+            .iter()
+            .all(|i| i.min > -INFINITY && i.max < INFINITY)
+    }
+
+    // 表面积，空盒子为0
+    pub fn surface_area(&self) -> f64 {
+        let (dx, dy, dz) = (self.x.size(), self.y.size(), self.z.size());
+        if dx < 0.0 || dy < 0.0 || dz < 0.0 {
+            return 0.0;
+        }
+        2.0 * (dx * dy + dy * dz + dz * dx)
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            0.5 * (self.x.min + self.x.max),
+            0.5 * (self.y.min + self.y.max),
+            0.5 * (self.z.min + self.z.max),
+        )
+    }
+
     pub fn longest_axis(&self) -> usize {
         if self.x.size() > self.y.size() {
             if self.x.size() > self.z.size() {
@@ -152,4 +182,33 @@ impl std::ops::Add<&Aabb> for Vec3 {
             z: self.z() + &rhs.z,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_axis_aligned_and_grazing_rays() {
+        let unit = Aabb::new_point(&Point3::new(0.0, 0.0, 0.0), &Point3::new(1.0, 1.0, 1.0));
+        let hit = |orig: Point3, dir: Vec3| unit.hit(&Ray::new(orig, dir), &mut Interval::new(0.0, INFINITY));
+
+        // 沿坐标轴的光线，其余分量为±0
+        assert!(hit(Point3::new(0.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0)));
+        assert!(hit(Point3::new(0.5, 0.5, 2.0), Vec3::new(-0.0, 0.0, -1.0)));
+        assert!(!hit(Point3::new(1.5, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0)));
+
+        // 起点正好在面上、沿面掠过的光线：0*∞为NaN，不应使测试失效
+        assert!(hit(Point3::new(0.0, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0)));
+        assert!(hit(Point3::new(1.0, 1.0, -1.0), Vec3::new(0.0, 0.0, 1.0)));
+        assert!(!hit(Point3::new(1.0 + 1e-9, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0)));
+
+        // 恰好擦过棱的斜向光线
+        assert!(hit(Point3::new(-1.0, 0.0, 0.5), Vec3::new(1.0, 1.0, 0.0)));
+        assert!(hit(Point3::new(2.0, 0.0, 0.5), Vec3::new(-1.0, 1.0, 0.0)));
+
+        // 厚度为0的盒子补齐到最小尺寸
+        let flat = Aabb::new_point(&Point3::new(0.0, 0.0, 0.0), &Point3::new(1.0, 0.0, 1.0));
+        assert!(flat.y.size() > 0.0);
+    }
+}
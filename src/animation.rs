@@ -0,0 +1,187 @@
+use crate::camera::Camera;
+use crate::vec3::{Point3, Vec3};
+
+// 相机在某一时刻的可动画参数
+#[derive(Clone, Copy, Debug)]
+pub struct CameraKey {
+    pub time: f64,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vfov: f64,
+    pub focus_dist: f64,
+    pub defocus_angle: f64,
+}
+
+impl CameraKey {
+    // 以相机当前参数作为一个关键帧
+    pub fn from_camera(time: f64, cam: &Camera) -> Self {
+        Self {
+            time,
+            lookfrom: cam.lookfrom,
+            lookat: cam.lookat,
+            vfov: cam.vfov,
+            focus_dist: cam.focus_dist,
+            defocus_angle: cam.defocus_angle,
+        }
+    }
+}
+
+// Catmull-Rom样条，曲线经过p1、p2
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f64) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2.0 * p1)
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    (1.0 - t) * a + t * b
+}
+
+// 相机关键帧轨道：位置用Catmull-Rom样条平滑插值，其余参数线性插值
+#[derive(Clone, Default)]
+pub struct CameraTrack {
+    keys: Vec<CameraKey>,
+}
+
+impl CameraTrack {
+    pub fn new(mut keys: Vec<CameraKey>) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { keys }
+    }
+
+    // 绕lookat所在的竖直轴转一圈的转台动画，steps个关键帧均匀分布在[start, end]上
+    pub fn turntable(cam: &Camera, start: f64, end: f64, steps: usize) -> Self {
+        let steps = steps.max(2);
+        let offset = cam.lookfrom - cam.lookat;
+        let keys = (0..=steps)
+            .map(|i| {
+                let s = i as f64 / steps as f64;
+                let (sin, cos) = (2.0 * std::f64::consts::PI * s).sin_cos();
+                let rotated = Vec3::new(cos * offset.x() + sin * offset.z(), offset.y(), -sin * offset.x() + cos * offset.z());
+                CameraKey {
+                    lookfrom: cam.lookat + rotated,
+                    ..CameraKey::from_camera(lerp(start, end, s), cam)
+                }
+            })
+            .collect();
+        Self::new(keys)
+    }
+
+    pub fn keys(&self) -> &[CameraKey] {
+        &self.keys
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // 把time时刻的参数写入相机；没有关键帧时相机保持不变
+    pub fn apply(&self, cam: &mut Camera, time: f64) {
+        let key = match self.sample(time) {
+            Some(key) => key,
+            None => return,
+        };
+        cam.lookfrom = key.lookfrom;
+        cam.lookat = key.lookat;
+        cam.vfov = key.vfov;
+        cam.focus_dist = key.focus_dist;
+        cam.defocus_angle = key.defocus_angle;
+    }
+
+    fn sample(&self, time: f64) -> Option<CameraKey> {
+        let keys = &self.keys;
+        let last = keys.len().checked_sub(1)?;
+        if time <= keys[0].time {
+            return Some(keys[0]);
+        }
+        if time >= keys[last].time {
+            return Some(keys[last]);
+        }
+        let i = keys.partition_point(|k| k.time <= time);
+        let (k1, k2) = (&keys[i - 1], &keys[i]);
+        // 两端缺少的控制点取端点本身
+        let k0 = &keys[i.saturating_sub(2)];
+        let k3 = &keys[(i + 1).min(last)];
+        let t = (time - k1.time) / (k2.time - k1.time);
+        Some(CameraKey {
+            time,
+            lookfrom: catmull_rom(k0.lookfrom, k1.lookfrom, k2.lookfrom, k3.lookfrom, t),
+            lookat: catmull_rom(k0.lookat, k1.lookat, k2.lookat, k3.lookat, t),
+            vfov: lerp(k1.vfov, k2.vfov, t),
+            focus_dist: lerp(k1.focus_dist, k2.focus_dist, t),
+            defocus_angle: lerp(k1.defocus_angle, k2.defocus_angle, t),
+        })
+    }
+}
+
+// 序列渲染的设置：第n帧的时刻为 n / fps，快门在该时刻打开，持续 shutter / fps
+#[derive(Clone, Debug)]
+pub struct Sequence {
+    pub first_frame: u32,
+    pub last_frame: u32, //包含
+    pub fps: f64,
+    pub shutter: f64, //快门占一帧时长的比例，0为无运动模糊，0.5相当于180度快门
+    pub output_dir: String,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self {
+            first_frame: 1,
+            last_frame: 24,
+            fps: 24.0,
+            shutter: 0.5,
+            output_dir: String::from("output/frames"),
+        }
+    }
+}
+
+impl Sequence {
+    pub fn frame_time(&self, frame: u32) -> f64 {
+        frame as f64 / self.fps
+    }
+
+    pub fn frame_path(&self, frame: u32) -> String {
+        format!("{}/frame_{:04}.png", self.output_dir, frame)
+    }
+
+    // 按动画轨道和快门设置得到某一帧使用的相机
+    pub fn camera_for_frame(&self, base: &Camera, track: &CameraTrack, frame: u32) -> Camera {
+        let time = self.frame_time(frame);
+        let mut cam = base.clone();
+        track.apply(&mut cam, time);
+        cam.shutter_open = time;
+        cam.shutter_close = time + self.shutter / self.fps;
+        cam
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_passes_through_keys() {
+        let mut cam = Camera::default();
+        cam.lookfrom = Point3::new(0.0, 2.0, 10.0);
+        cam.lookat = Point3::new(0.0, 1.0, 0.0);
+        let track = CameraTrack::turntable(&cam, 0.0, 4.0, 8);
+        let mut c = cam.clone();
+        for (time, expected) in [(0.0, Point3::new(0.0, 2.0, 10.0)), (1.0, Point3::new(10.0, 2.0, 0.0)), (2.0, Point3::new(0.0, 2.0, -10.0))] {
+            track.apply(&mut c, time);
+            assert!((c.lookfrom - expected).length() < 1e-9, "{:?}", c.lookfrom);
+        }
+        // 关键帧之间的位置仍然在圆附近
+        track.apply(&mut c, 1.25);
+        let d = ((c.lookfrom - cam.lookat) * Vec3::new(1.0, 0.0, 1.0)).length();
+        assert!(d > 9.9, "{}", d);
+
+        let seq = Sequence { fps: 2.0, shutter: 0.5, ..Sequence::default() };
+        let frame = seq.camera_for_frame(&cam, &track, 3);
+        assert_eq!((frame.shutter_open, frame.shutter_close), (1.5, 1.75));
+        assert_eq!(seq.frame_path(3), "output/frames/frame_0003.png");
+    }
+}
@@ -0,0 +1,108 @@
+use crate::rtw_stb_image::RtwImage;
+use crate::util::{self, random_double};
+use crate::vec3::Vec3;
+use std::sync::Arc;
+
+// 光圈形状，决定离焦处散景的形状
+#[derive(Clone, Default)]
+pub enum Aperture {
+    // 理想圆形光圈
+    #[default]
+    Circle,
+    // 正多边形光圈叶片，rotation为旋转角（度）
+    Blades { count: u32, rotation: f64 },
+    // 自定义散景形状，按图片亮度加权采样
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    // 在光圈上采样一点，坐标范围为[-1,1]，圆形光圈时即单位圆盘
+    pub fn sample(&self) -> Vec3 {
+        match self {
+            Aperture::Circle => Vec3::random_in_unit_disk(),
+            Aperture::Blades { count, rotation } => {
+                // 正多边形拆成count个以中心为顶点的三角形，先随机选三角形再在其内均匀采样
+                let n = (*count).max(3) as f64;
+                let k = (random_double() * n).floor();
+                let a0 = util::degrees_to_radians(*rotation) + 2.0 * util::PI * k / n;
+                let a1 = a0 + 2.0 * util::PI / n;
+                let (mut s, mut t) = (random_double(), random_double());
+                if s + t > 1.0 {
+                    s = 1.0 - s;
+                    t = 1.0 - t;
+                }
+                Vec3::new(s * a0.cos() + t * a1.cos(), s * a0.sin() + t * a1.sin(), 0.0)
+            }
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+// 图片光圈：亮度作为透过率，累积分布按像素重要性采样
+pub struct ApertureMask {
+    width: usize,
+    height: usize,
+    cdf: Vec<f64>,
+}
+
+impl ApertureMask {
+    // weights按行存储，从上到下
+    pub fn new(weights: &[f64], width: usize, height: usize) -> Self {
+        assert_eq!(weights.len(), width * height);
+        let mut cdf = Vec::with_capacity(weights.len());
+        let mut sum = 0.0;
+        for w in weights {
+            sum += w.max(0.0);
+            cdf.push(sum);
+        }
+        assert!(sum > 0.0, "aperture mask must not be black");
+        Self { width, height, cdf }
+    }
+
+    pub fn from_image(filename: &str) -> Self {
+        let image = RtwImage::new(filename);
+        let (width, height) = (image.width(), image.height());
+        let mut weights = Vec::with_capacity(width * height);
+        for j in 0..height {
+            for i in 0..width {
+                let pixel = image.pixel_data(i, j);
+                weights.push((pixel[0] as f64 + pixel[1] as f64 + pixel[2] as f64) / (3.0 * 255.0));
+            }
+        }
+        Self::new(&weights, width, height)
+    }
+
+    fn sample(&self) -> Vec3 {
+        let total = self.cdf[self.cdf.len() - 1];
+        let x = random_double() * total;
+        let index = self.cdf.partition_point(|&c| c <= x).min(self.cdf.len() - 1);
+        let (i, j) = (index % self.width, index / self.width);
+        // 较长的一边映射到[-1,1]，保持图片的宽高比
+        let scale = 2.0 / self.width.max(self.height) as f64;
+        let x = (i as f64 + random_double() - 0.5 * self.width as f64) * scale;
+        let y = (0.5 * self.height as f64 - j as f64 - random_double()) * scale;
+        Vec3::new(x, y, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_stay_inside() {
+        // 不旋转时正方形的顶点落在坐标轴上，|x|+|y|<=1
+        let square = Aperture::Blades { count: 4, rotation: 0.0 };
+        for _ in 0..1000 {
+            let p = square.sample();
+            assert!(p.x().abs() + p.y().abs() <= 1.0 + 1e-9, "{:?}", p);
+        }
+
+        // 只有右上角透光的2x2遮罩
+        let mask = Aperture::Mask(Arc::new(ApertureMask::new(&[0.0, 1.0, 0.0, 0.0], 2, 2)));
+        for _ in 0..1000 {
+            let p = mask.sample();
+            assert!((0.0..=1.0).contains(&p.x()) && (0.0..=1.0).contains(&p.y()), "{:?}", p);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::mesh::TriangleMesh;
+use crate::vec3::{Point3, Vec3};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+// 三次Bernstein基函数及其导数
+fn bernstein(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+fn bernstein_deriv(t: f64) -> [f64; 4] {
+    let s = 1.0 - t;
+    [-3.0 * s * s, 3.0 * s * s - 6.0 * t * s, 6.0 * t * s - 3.0 * t * t, 3.0 * t * t]
+}
+
+// 双三次Bézier面片，控制点按行存储：cp[4 * j + i]，i沿u方向，j沿v方向
+#[derive(Clone, Copy)]
+pub struct BezierPatch {
+    pub cp: [Point3; 16],
+}
+
+impl BezierPatch {
+    pub fn new(cp: [Point3; 16]) -> Self {
+        Self { cp }
+    }
+
+    fn combine(&self, bu: [f64; 4], bv: [f64; 4]) -> Vec3 {
+        let mut p = Vec3::zero();
+        for (row, &wv) in self.cp.chunks(4).zip(bv.iter()) {
+            for (&c, &wu) in row.iter().zip(bu.iter()) {
+                p += wu * wv * c;
+            }
+        }
+        p
+    }
+
+    pub fn eval(&self, u: f64, v: f64) -> Point3 {
+        self.combine(bernstein(u), bernstein(v))
+    }
+
+    pub fn normal(&self, u: f64, v: f64) -> Vec3 {
+        let du = self.combine(bernstein_deriv(u), bernstein(v));
+        let dv = self.combine(bernstein(u), bernstein_deriv(v));
+        let n = Vec3::cross(du, dv);
+        if !n.near_zero() {
+            return Vec3::unit_vector(n);
+        }
+        // 退化边（如茶壶壶盖顶点）处偏导为0，向面片内部挪一点再求
+        let eps = 1e-4;
+        let (u2, v2) = (u + if u < 0.5 { eps } else { -eps }, v + if v < 0.5 { eps } else { -eps });
+        let du = self.combine(bernstein_deriv(u2), bernstein(v2));
+        let dv = self.combine(bernstein(u2), bernstein_deriv(v2));
+        let n = Vec3::cross(du, dv);
+        if n.near_zero() { n } else { Vec3::unit_vector(n) }
+    }
+
+    // 在参数域上均匀细分为divisions x divisions个格子，每格两个三角形
+    pub fn tessellate(&self, divisions: usize) -> TriangleMesh {
+        let n = divisions.max(1);
+        let mut mesh = TriangleMesh::default();
+        for j in 0..=n {
+            for i in 0..=n {
+                let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+                mesh.positions.push(self.eval(u, v));
+                mesh.normals.push(self.normal(u, v));
+                mesh.uvs.push((u, v));
+            }
+        }
+        let idx = |i: usize, j: usize| j * (n + 1) + i;
+        for j in 0..n {
+            for i in 0..n {
+                mesh.indices.push([idx(i, j), idx(i + 1, j), idx(i + 1, j + 1)]);
+                mesh.indices.push([idx(i, j), idx(i + 1, j + 1), idx(i, j + 1)]);
+            }
+        }
+        mesh
+    }
+}
+
+// 多个面片细分后合并为一个网格
+pub fn tessellate_patches(patches: &[BezierPatch], divisions: usize) -> TriangleMesh {
+    let mut mesh = TriangleMesh::default();
+    for patch in patches {
+        mesh.append(&patch.tessellate(divisions));
+    }
+    mesh
+}
+
+// 解析经典的Newell茶壶格式：
+//   面片数
+//   每行16个从1开始的顶点索引（逗号或空格分隔）
+//   顶点数
+//   每行一个顶点 x y z
+pub fn parse_patches(text: &str) -> Result<Vec<BezierPatch>, String> {
+    let mut lines = text
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'));
+    // 每个面片和顶点至少占一行，按剩余行数限制预分配，避免文件头的计数过大
+    let line_count = lines.clone().count();
+    let mut next_fields = |what: &str| -> Result<Vec<&str>, String> {
+        let line = lines.next().ok_or(format!("unexpected end of file, expected {}", what))?;
+        let fields: Vec<&str> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if fields.is_empty() {
+            return Err(format!("no numbers in {}: \"{}\"", what, line));
+        }
+        Ok(fields)
+    };
+
+    let patch_count = parse_count(&next_fields("patch count")?, "patch count")?;
+    let mut patch_indices = Vec::with_capacity(patch_count.min(line_count));
+    for _ in 0..patch_count {
+        let idx = parse_numbers::<usize>(&next_fields("patch indices")?, "patch indices")?;
+        if idx.len() != 16 {
+            return Err(format!("patch has {} indices, expected 16", idx.len()));
+        }
+        patch_indices.push(idx);
+    }
+
+    let vertex_count = parse_count(&next_fields("vertex count")?, "vertex count")?;
+    let mut vertices = Vec::with_capacity(vertex_count.min(line_count));
+    for _ in 0..vertex_count {
+        let v = parse_numbers::<f64>(&next_fields("vertex")?, "vertex")?;
+        if v.len() != 3 {
+            return Err(format!("vertex has {} components, expected 3", v.len()));
+        }
+        vertices.push(Point3::new(v[0], v[1], v[2]));
+    }
+
+    patch_indices
+        .iter()
+        .map(|idx| {
+            let mut cp = [Point3::zero(); 16];
+            for (k, &i) in idx.iter().enumerate() {
+                if i == 0 || i > vertices.len() {
+                    return Err(format!("vertex index {} out of range", i));
+                }
+                cp[k] = vertices[i - 1];
+            }
+            Ok(BezierPatch::new(cp))
+        })
+        .collect()
+}
+
+// 计数和索引按整数解析，"1.5"或"-1"直接报错，不会被截断成别的值
+fn parse_numbers<T: FromStr>(fields: &[&str], what: &str) -> Result<Vec<T>, String>
+where
+    T::Err: fmt::Display,
+{
+    fields
+        .iter()
+        .map(|s| s.parse::<T>().map_err(|e| format!("bad number \"{}\" in {}: {}", s, what, e)))
+        .collect()
+}
+
+fn parse_count(fields: &[&str], what: &str) -> Result<usize, String> {
+    match parse_numbers::<usize>(fields, what)?[..] {
+        [count] => Ok(count),
+        _ => Err(format!("expected a single {}", what)),
+    }
+}
+
+pub fn load_patches(filename: &str) -> Vec<BezierPatch> {
+    let text = fs::read_to_string(filename)
+        .unwrap_or_else(|e| panic!("ERROR: Could not load patch file \"{}\": {}", filename, e));
+    parse_patches(&text).unwrap_or_else(|e| panic!("ERROR: Could not parse patch file \"{}\": {}", filename, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flat_patch() {
+        // 4x4平面网格，z=0
+        let mut text = String::from("1\n");
+        text += &(1..=16).map(|i| i.to_string()).collect::<Vec<_>>().join(", ");
+        text += "\n16\n";
+        for j in 0..4 {
+            for i in 0..4 {
+                text += &format!("{}, {}, 0.0\n", i, j);
+            }
+        }
+        let patches = parse_patches(&text).unwrap();
+        assert_eq!(patches.len(), 1);
+        let p = patches[0].eval(0.5, 0.25);
+        assert!((p - Point3::new(1.5, 0.75, 0.0)).length() < 1e-12);
+        assert!((patches[0].normal(0.3, 0.7) - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-12);
+
+        let mesh = patches[0].tessellate(4);
+        assert_eq!(mesh.positions.len(), 25);
+        assert_eq!(mesh.indices.len(), 32);
+
+        assert!(parse_patches("1\n1 2 3\n").is_err());
+        // 只有分隔符的行、非整数的计数和索引、过大的计数都返回错误而不是panic
+        assert!(parse_patches(",\n").is_err());
+        assert!(parse_patches("1\n , ,\n").is_err());
+        assert!(parse_patches("1.5\n").is_err());
+        assert!(parse_patches("18446744073709551615\n").is_err());
+        let fractional = text.replacen("1, 2,", "1.5, 2,", 1);
+        assert!(parse_patches(&fractional).err().unwrap().contains("patch indices"));
+    }
+}
@@ -5,6 +5,76 @@ use crate::ray::Ray;
 use crate::interval::Interval;
 use crate::aabb::Aabb;
 use crate::util::*;
+use crate::vec3::Point3;
+use std::thread;
+use crate::wide_bvh::DefaultBvh;
+use crate::packet::RayPacket;
+
+// BVH的划分方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BvhSplit {
+    // 随机选轴、按中位数划分，每个叶子一个物体
+    Median,
+    // 分桶表面积启发式：按代价选择划分轴和位置，物体数不超过leaf_size且不再划分更便宜时成为叶子
+    Sah { leaf_size: usize },
+}
+
+impl Default for BvhSplit {
+    fn default() -> Self {
+        BvhSplit::Sah { leaf_size: 4 }
+    }
+}
+
+const SAH_BINS: usize = 12;
+// 遍历一个节点相对于求交一个物体的代价
+pub(crate) const TRAVERSAL_COST: f64 = 0.125;
+// 物体数不少于此值的子树才交给新线程构建
+const PARALLEL_THRESHOLD: usize = 1 << 14;
+
+// 构建时每个物体的包围盒和中心，划分时只重排这个数组，不动物体本身
+#[derive(Clone)]
+pub(crate) struct BuildPrim {
+    pub(crate) bbox: Aabb,
+    pub(crate) centroid: Point3,
+    pub(crate) index: u32,
+}
+
+// 多线程分块计算所有物体的包围盒和中心
+pub(crate) fn build_prims(objects: &[Arc<dyn Hittable>]) -> Vec<BuildPrim> {
+    let threads = build_threads();
+    let chunk = objects.len().div_ceil(threads).max(PARALLEL_THRESHOLD);
+    thread::scope(|s| {
+        let handles: Vec<_> = objects
+            .chunks(chunk)
+            .enumerate()
+            .map(|(k, part)| {
+                s.spawn(move || {
+                    part.iter()
+                        .enumerate()
+                        .map(|(i, object)| {
+                            let bbox = object.bounding_box().clone();
+                            BuildPrim { centroid: bbox.centroid(), bbox, index: (k * chunk + i) as u32 }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+// 无界物体（如无限平面）会把整棵树的包围盒撑成无穷大，应先用new_boxed把它们分到树外
+pub(crate) fn assert_bounded(objects: &[Arc<dyn Hittable>]) {
+    assert!(
+        objects.iter().all(|object| object.bounding_box().is_bounded()),
+        "unbounded objects must be kept outside the BVH, see BvhNode::new_boxed"
+    );
+}
+
+fn build_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
 pub struct BvhNode {
     left: Arc<dyn Hittable>,
     right: Arc<dyn Hittable>,
@@ -13,17 +83,187 @@ pub struct BvhNode {
 
 impl BvhNode {
     pub fn new(list: &HittableList) -> Self {
-        let mut obj = list.objects.clone();
-        let l = list.objects.len();
-        // println!("{}balls",l);
-        Self::new_hitables(&mut obj, 0, l)
+        Self::new_with(list, BvhSplit::default())
+    }
+    pub fn new_with(list: &HittableList, split: BvhSplit) -> Self {
+        assert_bounded(&list.objects);
+        match split {
+            BvhSplit::Median => Self::from_objects(list.objects.clone(), split),
+            // SAH只重排BuildPrim，不需要复制物体数组
+            BvhSplit::Sah { leaf_size } => Self::new_sah(&list.objects, leaf_size.max(1)),
+        }
     }
+    fn from_objects(mut objects: Vec<Arc<dyn Hittable>>, split: BvhSplit) -> Self {
+        assert_bounded(&objects);
+        match split {
+            BvhSplit::Median => {
+                let l = objects.len();
+                Self::new_hitables(&mut objects, 0, l)
+            }
+            BvhSplit::Sah { leaf_size } => Self::new_sah(&objects, leaf_size.max(1)),
+        }
+    }
+    // 默认使用线性化的SAH BVH，开启bvh4/bvh8特性时为多路BVH
     pub fn new_boxed(
         list: &HittableList,
     ) -> Arc<dyn Hittable + Send + Sync> {
-        let mut obj = list.objects.clone();
-        let l = list.objects.len();
-        Arc::new(BvhNode::new_hitables(&mut obj,0,l))
+        Self::split_unbounded(list, |obj| Arc::new(DefaultBvh::from_objects(obj, BvhSplit::default())))
+    }
+    // 指针树形式的BVH，主要用于比较不同的构建方式
+    pub fn new_boxed_with(
+        list: &HittableList,
+        split: BvhSplit,
+    ) -> Arc<dyn Hittable + Send + Sync> {
+        Self::split_unbounded(list, |obj| Arc::new(Self::from_objects(obj, split)))
+    }
+
+    // 无界物体（如无限平面）不放进树里，在树外逐个检测
+    fn split_unbounded(
+        list: &HittableList,
+        build: impl FnOnce(Vec<Arc<dyn Hittable>>) -> Arc<dyn Hittable + Send + Sync>,
+    ) -> Arc<dyn Hittable + Send + Sync> {
+        let (obj, unbounded): (Vec<_>, Vec<_>) = list.objects.iter()
+            .cloned()
+            .partition(|object| object.bounding_box().is_bounded());
+        if unbounded.is_empty() {
+            return build(obj);
+        }
+
+        let mut top = HittableList::default();
+        if !obj.is_empty() {
+            top.add(build(obj));
+        }
+        for object in unbounded {
+            top.add(object);
+        }
+        Arc::new(top)
+    }
+
+    // 根节点总是划分，保证返回的是BvhNode
+    fn new_sah(objects: &[Arc<dyn Hittable>], leaf_size: usize) -> Self {
+        if objects.len() == 1 {
+            return Self {
+                left: objects[0].clone(),
+                right: objects[0].clone(),
+                bbox: objects[0].bounding_box().clone(),
+            };
+        }
+        let mut prims = build_prims(objects);
+        let mid = Self::sah_partition(&mut prims, 1).unwrap();
+        Self::sah_node(objects, &mut prims, mid, leaf_size, build_threads())
+    }
+
+    // 子树足够大且还有空闲线程时，左子树在新线程中构建
+    fn sah_node(objects: &[Arc<dyn Hittable>], prims: &mut [BuildPrim], mid: usize, leaf_size: usize, threads: usize) -> Self {
+        let parallel = threads > 1 && prims.len() >= PARALLEL_THRESHOLD;
+        let (left, right) = prims.split_at_mut(mid);
+        let (left, right) = if parallel {
+            thread::scope(|s| {
+                let handle = s.spawn(|| Self::build_sah(objects, left, leaf_size, threads / 2));
+                let right = Self::build_sah(objects, right, leaf_size, threads - threads / 2);
+                (handle.join().unwrap(), right)
+            })
+        } else {
+            (Self::build_sah(objects, left, leaf_size, 1), Self::build_sah(objects, right, leaf_size, 1))
+        };
+        let bbox = Aabb::new_box(left.bounding_box(), right.bounding_box());
+        Self { left, right, bbox }
+    }
+
+    fn build_sah(objects: &[Arc<dyn Hittable>], prims: &mut [BuildPrim], leaf_size: usize, threads: usize) -> Arc<dyn Hittable> {
+        if prims.len() == 1 {
+            return objects[prims[0].index as usize].clone();
+        }
+        match Self::sah_partition(prims, leaf_size) {
+            Some(mid) => Arc::new(Self::sah_node(objects, prims, mid, leaf_size, threads)),
+            None => {
+                let mut leaf = HittableList::default();
+                for prim in prims.iter() {
+                    leaf.add(objects[prim.index as usize].clone());
+                }
+                Arc::new(leaf)
+            }
+        }
+    }
+
+    // 按物体包围盒中心分桶，在三个轴的桶边界中找代价最小的划分，并原地重排prims；
+    // 返回左半部分的数量，None表示作为叶子更划算
+    pub(crate) fn sah_partition(prims: &mut [BuildPrim], leaf_size: usize) -> Option<usize> {
+        let n = prims.len();
+        let mut bbox = Aabb::default();
+        let mut cmin = [INFINITY; 3];
+        let mut cmax = [-INFINITY; 3];
+        for prim in prims.iter() {
+            bbox = Aabb::new_box(&bbox, &prim.bbox);
+            for a in 0..3 {
+                cmin[a] = cmin[a].min(prim.centroid[a]);
+                cmax[a] = cmax[a].max(prim.centroid[a]);
+            }
+        }
+
+        let bin_of = |prim: &BuildPrim, axis: usize| -> usize {
+            let b = ((prim.centroid[axis] - cmin[axis]) / (cmax[axis] - cmin[axis]) * SAH_BINS as f64) as usize;
+            b.min(SAH_BINS - 1)
+        };
+
+        let total_area = bbox.surface_area().max(1e-12);
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in 0..3 {
+            if cmax[axis] - cmin[axis] <= 0.0 {
+                continue;
+            }
+            let mut counts = [0usize; SAH_BINS];
+            let mut boxes: [Aabb; SAH_BINS] = std::array::from_fn(|_| Aabb::default());
+            for prim in prims.iter() {
+                let b = bin_of(prim, axis);
+                counts[b] += 1;
+                boxes[b] = Aabb::new_box(&boxes[b], &prim.bbox);
+            }
+
+            // 先从右往左累积右侧，再从左往右扫描每个划分位置
+            let mut right_area = [0.0; SAH_BINS];
+            let mut right_count = [0usize; SAH_BINS];
+            let mut acc = Aabb::default();
+            let mut acc_count = 0;
+            for b in (1..SAH_BINS).rev() {
+                acc = Aabb::new_box(&acc, &boxes[b]);
+                acc_count += counts[b];
+                right_area[b - 1] = acc.surface_area();
+                right_count[b - 1] = acc_count;
+            }
+            let mut acc = Aabb::default();
+            let mut acc_count = 0;
+            for b in 0..SAH_BINS - 1 {
+                acc = Aabb::new_box(&acc, &boxes[b]);
+                acc_count += counts[b];
+                if acc_count == 0 || right_count[b] == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST
+                    + (acc.surface_area() * acc_count as f64 + right_area[b] * right_count[b] as f64) / total_area;
+                if best.is_none_or(|(c, _, _)| cost < c) {
+                    best = Some((cost, axis, b));
+                }
+            }
+        }
+
+        let (cost, axis, split) = match best {
+            Some(best) => best,
+            // 所有中心重合，无法按位置划分
+            None => return if n <= leaf_size { None } else { Some(n / 2) },
+        };
+        if n <= leaf_size && cost >= n as f64 {
+            return None;
+        }
+
+        let mut mid = 0;
+        for i in 0..n {
+            if bin_of(&prims[i], axis) <= split {
+                prims.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some(mid)
     }
     // pub fn new_hitable(src_objects: &mut Vec<Arc<dyn Hittable>>, start: usize, end: usize) -> Self {
     //     let mut bbox = Aabb::default();
@@ -144,16 +384,24 @@ impl BvhNode {
 }
 
 impl Hittable for BvhNode {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
         let mut ray_t = ray_t.clone();
         if !self.bbox.hit(r, &mut ray_t) {
            
             return false;
         }
 
-        let hit_left = self.left.hit(r, &ray_t, rec);
+        let hit_left = self.left.hit_deferred(r, &ray_t, rec);
         let ray_t = Interval::new(ray_t.min, if hit_left { rec.t } else { ray_t.max });
-        let hit_right = self.right.hit(r, &ray_t, rec);
+        let hit_right = self.right.hit_deferred(r, &ray_t, rec);
 
         // if hit_left || hit_right {println!("get it!");}
         // else { println!("not hit");}
@@ -163,4 +411,377 @@ impl Hittable for BvhNode {
     fn bounding_box(&self) -> &Aabb {
         &self.bbox
     }
-}
\ No newline at end of file
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.bbox.hit(r, &mut ray_t.clone()) && (self.left.occluded(r, ray_t) || self.right.occluded(r, ray_t))
+    }
+}
+
+// 线性化BVH的节点：叶子时offset为第一个物体的下标，内部节点时为右子节点（左子节点紧随其后）
+pub(crate) struct LinearNode {
+    pub(crate) bbox: Aabb,
+    pub(crate) offset: u32,
+    pub(crate) count: u32,
+    axis: u8,
+}
+
+const FLAT_MAX_DEPTH: usize = 64;
+
+// 线性化的BVH：节点按深度优先顺序存放在连续数组中，物体按叶子顺序存放、用下标引用，
+// 遍历时用显式栈代替递归，并按光线方向先访问较近的子节点
+pub struct FlatBvh {
+    pub(crate) nodes: Vec<LinearNode>,
+    pub(crate) objects: Vec<Arc<dyn Hittable>>,
+}
+
+impl FlatBvh {
+    pub fn new(list: &HittableList) -> Self {
+        Self::from_objects(list.objects.clone(), BvhSplit::default())
+    }
+
+    // 取得物体数组的所有权，不复制
+    pub fn from_objects(objects: Vec<Arc<dyn Hittable>>, split: BvhSplit) -> Self {
+        assert!(!objects.is_empty(), "FlatBvh needs at least one object");
+        assert_bounded(&objects);
+        let leaf_size = match split {
+            BvhSplit::Median => 1,
+            BvhSplit::Sah { leaf_size } => leaf_size.max(1),
+        };
+        let mut prims = build_prims(&objects);
+        let nodes = Self::build_parallel(&mut prims, 0, leaf_size, 0, build_threads());
+
+        // 按叶子顺序重排物体，移动而不复制
+        let mut slots: Vec<Option<Arc<dyn Hittable>>> = objects.into_iter().map(Some).collect();
+        let objects = prims.iter().map(|p| slots[p.index as usize].take().unwrap()).collect();
+        Self { nodes, objects }
+    }
+
+    // 并行构建子树，返回的节点下标从0开始；叶子的offset是全局的物体下标，无需调整
+    fn build_parallel(prims: &mut [BuildPrim], start: usize, leaf_size: usize, depth: usize, threads: usize) -> Vec<LinearNode> {
+        let split = if threads > 1 && prims.len() >= PARALLEL_THRESHOLD && depth + 1 < FLAT_MAX_DEPTH {
+            BvhNode::sah_partition(prims, leaf_size)
+        } else {
+            None
+        };
+        let mid = match split {
+            Some(mid) => mid,
+            None => {
+                let mut nodes = Vec::with_capacity(2 * prims.len());
+                Self::build(prims, start, leaf_size, depth, &mut nodes);
+                return nodes;
+            }
+        };
+
+        let (left, right) = prims.split_at_mut(mid);
+        let (left, right) = thread::scope(|s| {
+            let handle = s.spawn(|| Self::build_parallel(left, start, leaf_size, depth + 1, threads / 2));
+            let right = Self::build_parallel(right, start + mid, leaf_size, depth + 1, threads - threads / 2);
+            (handle.join().unwrap(), right)
+        });
+
+        let right_index = 1 + left.len();
+        let mut nodes = Vec::with_capacity(right_index + right.len());
+        nodes.push(Self::interior(&left[0], &right[0], right_index));
+        for (nodes_part, shift) in [(left, 1), (right, right_index)] {
+            nodes.extend(nodes_part.into_iter().map(|mut node| {
+                if node.count == 0 {
+                    node.offset += shift as u32;
+                }
+                node
+            }));
+        }
+        nodes
+    }
+
+    fn build(prims: &mut [BuildPrim], start: usize, leaf_size: usize, depth: usize, nodes: &mut Vec<LinearNode>) -> usize {
+        let index = nodes.len();
+        // 栈的深度有限，过深时直接作为叶子
+        let split = if prims.len() == 1 || depth + 1 >= FLAT_MAX_DEPTH {
+            None
+        } else {
+            BvhNode::sah_partition(prims, leaf_size)
+        };
+        let mid = match split {
+            Some(mid) => mid,
+            None => {
+                let mut bbox = Aabb::default();
+                for prim in prims.iter() {
+                    bbox = Aabb::new_box(&bbox, &prim.bbox);
+                }
+                nodes.push(LinearNode { bbox, offset: start as u32, count: prims.len() as u32, axis: 0 });
+                return index;
+            }
+        };
+
+        // 先占位，子树建好后再填
+        nodes.push(LinearNode { bbox: Aabb::default(), offset: 0, count: 0, axis: 0 });
+        let (left, right) = prims.split_at_mut(mid);
+        Self::build(left, start, leaf_size, depth + 1, nodes);
+        let right_index = Self::build(right, start + mid, leaf_size, depth + 1, nodes);
+        nodes[index] = Self::interior(&nodes[index + 1], &nodes[right_index], right_index);
+        index
+    }
+
+    // 沿左右子节点中心相距最远的轴排序遍历；右子节点在该轴负方向时，光线方向为正应先访问右侧，用最高位记录
+    fn interior(left: &LinearNode, right: &LinearNode, right_index: usize) -> LinearNode {
+        let d = right.bbox.centroid() - left.bbox.centroid();
+        let axis = (0..3).max_by(|&a, &b| d[a].abs().total_cmp(&d[b].abs())).unwrap();
+        LinearNode {
+            bbox: Aabb::new_box(&left.bbox, &right.bbox),
+            offset: right_index as u32,
+            count: 0,
+            axis: axis as u8 | if d[axis] < 0.0 { 0x80 } else { 0 },
+        }
+    }
+}
+
+impl Hittable for FlatBvh {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let mut closest = ray_t.clone();
+        let mut hit_anything = false;
+
+        let mut stack = [0u32; FLAT_MAX_DEPTH];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let index = stack[top] as usize;
+            let node = &self.nodes[index];
+            if !node.bbox.hit(r, &mut closest.clone()) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for object in &self.objects[start..start + node.count as usize] {
+                    if object.hit_deferred(r, &closest, rec) {
+                        hit_anything = true;
+                        closest.max = rec.t;
+                    }
+                }
+            } else {
+                let (left, right) = (index as u32 + 1, node.offset);
+                let axis = (node.axis & 0x7f) as usize;
+                let flipped = node.axis & 0x80 != 0;
+                let (near, far) = if r.dir_is_neg(axis) != flipped { (right, left) } else { (left, right) };
+                stack[top] = far;
+                stack[top + 1] = near;
+                top += 2;
+            }
+        }
+        hit_anything
+    }
+
+    // 找到任意交点即返回，子节点的访问顺序不影响结果，按左右顺序入栈
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        let mut stack = [0u32; FLAT_MAX_DEPTH];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let index = stack[top] as usize;
+            let node = &self.nodes[index];
+            if !node.bbox.hit(r, &mut ray_t.clone()) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                if self.objects[start..start + node.count as usize].iter().any(|object| object.occluded(r, ray_t)) {
+                    return true;
+                }
+            } else {
+                stack[top] = node.offset;
+                stack[top + 1] = index as u32 + 1;
+                top += 2;
+            }
+        }
+        false
+    }
+
+    // 整包一起遍历（first-hit ranged traversal）：每个节点从父节点的第一条命中光线开始，
+    // 找到第一条与包围盒相交的活动光线就向下，相干光线通常只需测一条；
+    // 到叶子时排在这条光线之前的光线都已错过，从active中去掉，其余交给图元逐通道求交。
+    // 遍历顺序取这条光线的方向
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        let active = packet.active;
+        let mut stack = [(0u32, 0u8); FLAT_MAX_DEPTH];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let (index, first) = stack[top];
+            let node = &self.nodes[index as usize];
+            let Some(first) = packet.first_hit(&node.bbox, first as usize) else {
+                continue;
+            };
+            if node.count > 0 {
+                let start = node.offset as usize;
+                packet.active[..first].fill(false);
+                for object in &self.objects[start..start + node.count as usize] {
+                    object.hit_packet(packet);
+                }
+                packet.active = active;
+            } else {
+                let (left, right) = (index + 1, node.offset);
+                let axis = (node.axis & 0x7f) as usize;
+                let flipped = node.axis & 0x80 != 0;
+                let (near, far) = if packet.rays[first].dir_is_neg(axis) != flipped { (right, left) } else { (left, right) };
+                stack[top] = (far, first as u8);
+                stack[top + 1] = (near, first as u8);
+                top += 2;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.nodes[0].bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    #[test]
+    fn test_bvh_matches_brute_force() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::default();
+        for _ in 0..500 {
+            let center = Point3::random_range(-10.0, 10.0);
+            list.add(Arc::new(Sphere::new(center, random_double_range(0.05, 0.5), mat.clone())));
+        }
+        let bvh = BvhNode::new_with(&list, BvhSplit::Sah { leaf_size: 4 });
+        let flat = FlatBvh::new(&list);
+
+        for _ in 0..500 {
+            let r = Ray::new(Point3::random_range(-12.0, 12.0), Vec3::random_range(-1.0, 1.0));
+            let (mut a, mut b) = (HitRecord::default(), HitRecord::default());
+            let t = Interval::new(0.001, INFINITY);
+            let mut c = HitRecord::default();
+            let hit = list.hit(&r, &t, &mut a);
+            assert_eq!(hit, bvh.hit(&r, &t, &mut b));
+            assert_eq!(hit, flat.hit(&r, &t, &mut c));
+            assert_eq!(a.t, b.t);
+            assert_eq!(a.t, c.t);
+        }
+    }
+
+    #[test]
+    fn test_parallel_build_matches_sequential() {
+        // 直接指定线程数，单核机器上也走并行路径；划分是确定的，两种构建应得到相同的节点数组
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let objects: Vec<Arc<dyn Hittable>> = (0..PARALLEL_THRESHOLD * 3)
+            .map(|_| Arc::new(Sphere::new(Point3::random_range(-100.0, 100.0), 0.1, mat.clone())) as Arc<dyn Hittable>)
+            .collect();
+        let mut prims = build_prims(&objects);
+        let mut prims_seq = prims.clone();
+
+        let parallel = FlatBvh::build_parallel(&mut prims, 0, 4, 0, 4);
+        let mut sequential = Vec::new();
+        FlatBvh::build(&mut prims_seq, 0, 4, 0, &mut sequential);
+
+        assert_eq!(parallel.len(), sequential.len());
+        for (p, q) in parallel.iter().zip(&sequential) {
+            assert_eq!((p.offset, p.count, p.axis), (q.offset, q.count, q.axis));
+            assert_eq!(p.bbox.centroid(), q.bbox.centroid());
+        }
+        assert!(prims.iter().zip(&prims_seq).all(|(a, b)| a.index == b.index));
+    }
+
+    #[test]
+    fn test_occluded_matches_hit() {
+        use crate::capsule::Capsule;
+        use crate::cone::Cone;
+        use crate::cylinder::Cylinder;
+        use crate::hittable::RotateY;
+        use crate::mesh::TriangleMesh;
+        use crate::qard::Quad;
+        use crate::torus::Torus;
+
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::default();
+        for _ in 0..20 {
+            list.add(Arc::new(Sphere::new(Point3::random_range(-8.0, 8.0), random_double_range(0.2, 1.0), mat.clone())));
+        }
+        list.add(Arc::new(Quad::new(Point3::new(-6.0, -6.0, -6.0), Vec3::new(12.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 12.0), mat.clone())));
+        list.add(Arc::new(Cylinder::new(Point3::new(2.0, -3.0, 1.0), Point3::new(2.0, 3.0, 1.0), 0.8, mat.clone())));
+        list.add(Arc::new(Cone::new(Point3::new(-3.0, -2.0, 2.0), Point3::new(-3.0, 2.0, 2.0), 1.2, mat.clone())));
+        list.add(Arc::new(Capsule::new(Point3::new(-4.0, 3.0, -4.0), Point3::new(4.0, 3.0, -2.0), 0.6, mat.clone())));
+        list.add(Arc::new(RotateY::new(Arc::new(Torus::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), 2.0, 0.5, mat.clone())), 30.0)));
+        let mesh = TriangleMesh::new(
+            vec![Point3::new(-5.0, 5.0, -5.0), Point3::new(5.0, 5.0, -5.0), Point3::new(0.0, 5.0, 5.0)],
+            vec![[0, 1, 2]],
+        );
+        list.add(mesh.into_hittable(mat));
+        let bvh = BvhNode::new_with(&list, BvhSplit::Sah { leaf_size: 2 });
+        let flat = FlatBvh::new(&list);
+
+        for _ in 0..2000 {
+            let r = Ray::new(Point3::random_range(-10.0, 10.0), Vec3::random_range(-1.0, 1.0));
+            // 有限区间覆盖交点恰在区间外的情况
+            let t = Interval::new(0.001, random_double_range(0.5, 20.0));
+            for object in &list.objects {
+                assert_eq!(object.occluded(&r, &t), object.hit(&r, &t, &mut HitRecord::default()));
+            }
+            let hit = list.hit(&r, &t, &mut HitRecord::default());
+            assert_eq!(hit, list.occluded(&r, &t));
+            assert_eq!(hit, bvh.occluded(&r, &t));
+            assert_eq!(hit, flat.occluded(&r, &t));
+        }
+    }
+
+    #[test]
+    fn test_unbounded_objects_stay_outside_tree() {
+        use crate::plane::Plane;
+
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::default();
+        list.add(Arc::new(Sphere::new(Point3::zero(), 1.0, mat.clone())));
+        list.add(Arc::new(Plane::new(Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0), mat.clone())));
+        list.add(Arc::new(Sphere::new(Point3::new(3.0, 0.0, 0.0), 1.0, mat)));
+
+        // 平面不进树，树的包围盒只包住两个球
+        let mut tree_box = None;
+        BvhNode::split_unbounded(&list, |obj| {
+            assert_eq!(obj.len(), 2);
+            let bvh = Arc::new(FlatBvh::from_objects(obj, BvhSplit::default()));
+            tree_box = Some(bvh.bounding_box().clone());
+            bvh
+        });
+        let tree_box = tree_box.unwrap();
+        assert!(tree_box.is_bounded());
+        assert!(tree_box.axis(0).min > -1.1 && tree_box.axis(0).max < 4.1);
+        assert!(tree_box.axis(1).min > -1.1 && tree_box.axis(1).max < 1.1);
+
+        // 远离两个球的光线仍然击中平面，球上方的光线先击中球
+        for world in [BvhNode::new_boxed(&list), BvhNode::new_boxed_with(&list, BvhSplit::Median)] {
+            let t = Interval::new(0.001, INFINITY);
+            let mut rec = HitRecord::default();
+            assert!(world.hit(&Ray::new(Point3::new(100.0, 0.0, -100.0), Vec3::new(0.0, -1.0, 0.0)), &t, &mut rec));
+            assert!((rec.t - 5.0).abs() < 1e-8);
+            assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-8);
+            assert!(world.hit(&Ray::new(Point3::new(3.3, 5.0, 0.4), Vec3::new(0.0, -1.0, 0.0)), &t, &mut rec));
+            assert!((rec.t - (5.0 - 0.75f64.sqrt())).abs() < 1e-8);
+            assert!(world.occluded(&Ray::new(Point3::new(-50.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), &t));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "unbounded objects")]
+    fn test_flat_bvh_rejects_unbounded_objects() {
+        use crate::plane::Plane;
+
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::new(Arc::new(Sphere::new(Point3::zero(), 1.0, mat.clone())));
+        list.add(Arc::new(Plane::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), mat)));
+        FlatBvh::new(&list);
+    }
+
+}
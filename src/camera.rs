@@ -12,10 +12,85 @@ use std::sync::{Arc, Mutex};
 use crate::hittable_list::HittableList;
 use std::thread;
 use crate::util;
+use crate::aperture::Aperture;
+use crate::packet::{RayPacket, MAX_PACKET};
 pub const INFINITY: f64 = std::f64::INFINITY;
 use std::fs::File;
 const INTENSITY: Interval = Interval{ min: 0.0, max: 0.999 };
 const AUTHOR: &str = "box fish";
+
+// 相机的投影方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // 薄透镜透视投影，使用vfov和景深参数
+    Perspective,
+    // 正交投影，height为视野在世界空间中的高度
+    Orthographic { height: f64 },
+    // 等距鱼眼，fov为成像圆直径对应的视角（角度），圆外为黑色
+    Fisheye { fov: f64 },
+    // 等距柱状全景，横向360度、纵向180度，宽高比应为2:1
+    Equirectangular,
+}
+
+// 双目图像的拼接方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoLayout {
+    SideBySide,
+    OverUnder,
+}
+
+// 着色方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    // 路径追踪，按材质散射并累积自发光
+    PathTracing,
+    // 环境光遮蔽：在交点的法线半球内按余弦分布发出samples条光线，distance内未被遮挡的比例即为亮度，忽略材质
+    AmbientOcclusion { samples: u32, distance: f64 },
+}
+
+// 物理相机参数：f数决定景深，快门时间决定运动模糊区间，三者与ISO、曝光补偿一起决定画面亮度
+// 亮度按光度学约定：辐亮度以cd/m²理解，时间以秒为单位
+#[derive(Clone, Copy, Debug)]
+pub struct PhysicalExposure {
+    pub f_number: f64,
+    pub shutter_time: f64,//秒
+    pub iso: f64,
+    pub exposure_compensation: f64,//EV，正值更亮
+    pub sensor_height: f64,//毫米，与vfov一起换算焦距，默认全画幅24mm
+    pub units_per_meter: f64,//场景中一米对应的长度单位
+}
+
+impl Default for PhysicalExposure {
+    // 晴天16法则：f/16，1/100秒，ISO 100
+    fn default() -> Self {
+        Self {
+            f_number: 16.0,
+            shutter_time: 1.0 / 100.0,
+            iso: 100.0,
+            exposure_compensation: 0.0,
+            sensor_height: 24.0,
+            units_per_meter: 1.0,
+        }
+    }
+}
+
+impl PhysicalExposure {
+    // ISO 100下的曝光值
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter_time * 100.0 / self.iso).log2()
+    }
+
+    // 辐亮度到像素值的缩放：饱和亮度取 1.2 * 2^EV100（镜头透过率与传感器的常用标定）
+    pub fn exposure_scale(&self) -> f64 {
+        1.0 / (1.2 * 2f64.powf(self.ev100() - self.exposure_compensation))
+    }
+
+    // 由vfov得到焦距（毫米）
+    pub fn focal_length(&self, vfov: f64) -> f64 {
+        0.5 * self.sensor_height / (util::degrees_to_radians(vfov) / 2.0).tan()
+    }
+}
+
 #[derive(Clone)]
 pub struct Camera {
     pub aspect_ratio: f64,  
@@ -39,6 +114,21 @@ pub struct Camera {
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
     pub background: Color,
+    pub shutter_open: f64,//快门打开和关闭的时刻，光线时间在其间均匀采样
+    pub shutter_close: f64,
+    pub projection: Projection,
+    pub eye_offset: f64,//双目时沿右方向的偏移，左眼为负；全景投影下随光线方向变化（ODS）
+    pub aperture: Aperture,//光圈形状，决定散景形状
+    pub cat_eye: f64,//猫眼渐晕强度[0,1]，0为关闭；画面边缘的光圈被镜筒裁切，散景呈橄榄形且变暗
+    pub focus_tilt: f64,//移轴：焦平面绕水平轴倾斜的角度，正值时画面上方的焦平面更远
+    pub focus_swing: f64,//移轴：焦平面绕竖直轴旋转的角度，正值时画面右侧的焦平面更远
+    focus_normal: Vec3,
+    pub exposure: Option<PhysicalExposure>,//为None时不缩放亮度，景深和快门使用defocus_angle与shutter_open/close
+    exposure_scale: f64,
+    lens_defocus_angle: f64,//实际使用的散焦角和快门关闭时刻，由exposure推出或取用户设置，不改写上面的配置
+    lens_shutter_close: f64,
+    pub packet_size: usize,//主光线按包追踪时每包的光线数（4、8或16），0为逐条追踪
+    pub integrator: Integrator,
 }
 impl Default for Camera {
     fn default() -> Self {
@@ -64,6 +154,21 @@ impl Default for Camera {
             defocus_disk_u: Vec3::default(),
             defocus_disk_v: Vec3::default(),
             background: Color::default(),
+            shutter_open: 0.0,
+            shutter_close: 1.0,
+            projection: Projection::Perspective,
+            eye_offset: 0.0,
+            aperture: Aperture::Circle,
+            cat_eye: 0.0,
+            focus_tilt: 0.0,
+            focus_swing: 0.0,
+            focus_normal: Vec3::default(),
+            exposure: None,
+            exposure_scale: 1.0,
+            lens_defocus_angle: 0.0,
+            lens_shutter_close: 1.0,
+            packet_size: 0,
+            integrator: Integrator::PathTracing,
         }
     }
 }
@@ -89,12 +194,19 @@ impl Camera {
         if !world.hit(r, &Interval::new(0.001, util::INFINITY), &mut rec) {
             return self.background;
         }
+        self.shade(r, &rec, depth, world)
+    }
 
-        if let Some(mat) = rec.mat.clone() {
+    // 已知光线的第一个交点时计算颜色，光线包求交后的主光线从这里逐条继续弹射
+    pub fn shade(&self, r: &Ray, rec: &HitRecord, depth: i32, world: &dyn Hittable) -> Color {
+        if let Integrator::AmbientOcclusion { samples, distance } = self.integrator {
+            return Self::ambient_occlusion(r, rec, world, samples, distance);
+        }
+        if let Some(mat) = rec.mat {
             let mut scattered = Ray::default();
             let mut attenuation = Color::default();
             let color_from_emission = mat.emitted(rec.u, rec.v, rec.p);
-            if !mat.scatter(r, &rec, &mut attenuation, &mut scattered) {
+            if !mat.scatter(r, rec, &mut attenuation, &mut scattered) {
                 return color_from_emission;
             }
 
@@ -107,10 +219,38 @@ impl Camera {
     }
 
 
+    // 遮蔽光线只需知道有没有交点，用occluded而不求最近交点
+    fn ambient_occlusion(r: &Ray, rec: &HitRecord, world: &dyn Hittable, samples: u32, distance: f64) -> Color {
+        let samples = samples.max(1);
+        let mut visible = 0;
+        for _ in 0..samples {
+            let mut direction = rec.normal + Vec3::random_unit_vector();
+            if direction.near_zero() {
+                direction = rec.normal;
+            }
+            let occlusion_ray = Ray::new_time(rec.p, direction, r.tm);
+            // 方向未归一化，区间按长度换算
+            if !world.occluded(&occlusion_ray, &Interval::new(0.001, distance / direction.length())) {
+                visible += 1;
+            }
+        }
+        Color::ones() * (visible as f64 / samples as f64)
+    }
+
     pub fn initialize(&mut self) {
         self.image_height = 450;
 
         self.center = self.lookfrom;
+        self.exposure_scale = 1.0;
+        self.lens_defocus_angle = self.defocus_angle;
+        self.lens_shutter_close = self.shutter_close;
+        if let Some(exposure) = self.exposure {
+            // 光圈直径 = 焦距 / f数，换算到场景单位后得到对焦处的散焦角
+            let aperture_radius = 0.5 * exposure.focal_length(self.vfov) / exposure.f_number / 1000.0 * exposure.units_per_meter;
+            self.lens_defocus_angle = 2.0 * (aperture_radius / self.focus_dist).atan().to_degrees();
+            self.lens_shutter_close = self.shutter_open + exposure.shutter_time;
+            self.exposure_scale = exposure.exposure_scale();
+        }
         // let focal_length = (self.lookfrom - self.lookat).length();
 
         self.w = Vec3::unit_vector(self.lookfrom-self.lookat);
@@ -135,9 +275,19 @@ impl Camera {
         let viewport_upper_left = self.center
             - (self.focus_dist * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
-        let defocus_radius = self.focus_dist * (util::degrees_to_radians(self.defocus_angle / 2.0)).tan();
+        let defocus_radius = self.focus_dist * (util::degrees_to_radians(self.lens_defocus_angle / 2.0)).tan();
         self.defocus_disk_u = self.u * defocus_radius;
         self.defocus_disk_v = self.v * defocus_radius;
+
+        // 焦平面过视窗中心，不倾斜时法线即w
+        let tilt = util::degrees_to_radians(self.focus_tilt).tan();
+        let swing = util::degrees_to_radians(self.focus_swing).tan();
+        self.focus_normal = Vec3::unit_vector(self.w + tilt * self.v + swing * self.u);
+    }
+
+    // 线性辐亮度在gamma校正前乘以的系数，须在initialize之后调用
+    pub fn exposure_scale(&self) -> f64 {
+        self.exposure_scale
     }
 
     pub fn is_ci() -> bool {
@@ -150,27 +300,143 @@ impl Camera {
         px * self.pixel_delta_u + py * self.pixel_delta_v
     }
 
-    pub fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
-        self.center + p.x() * self.defocus_disk_u + p.y() * self.defocus_disk_v
+    // 在光圈上采样透镜位置；offset为像素相对画面中心的位置（对角线一半为1），
+    // 猫眼渐晕时光圈与一个向画面中心偏移的同样大小的圆取交集，落在交集外的光线被镜筒挡住
+    pub fn defocus_disk_sample(&self, offset: (f64, f64)) -> Option<Point3> {
+        let p = self.aperture.sample();
+        let k = self.cat_eye.clamp(0.0, 1.0);
+        let (dx, dy) = (p.x() + k * offset.0, p.y() + k * offset.1);
+        if k > 0.0 && dx * dx + dy * dy > 1.0 {
+            return None;
+        }
+        Some(self.center + p.x() * self.defocus_disk_u + p.y() * self.defocus_disk_v)
     }
 
 
-    pub fn get_ray(&self, i: u32, j: u32) -> Ray {
-        // Get a randomly sampled camera ray for the pixel at location i,j.
-        let pixel_center = self.pixel00_loc + i as f64 * self.pixel_delta_u + j as f64 * self.pixel_delta_v;
-        let pixel_sample = pixel_center + self.sample_square();
-    
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center
-        } else {
-            self.defocus_disk_sample()
-        };
-        let ray_direction = pixel_sample - ray_origin;
-        let ray_time = random_double();
-        Ray::new_time(ray_origin, ray_direction,ray_time)
+    // 光线包覆盖的像素块的宽和高
+    pub fn packet_tile(&self) -> (u32, u32) {
+        match self.packet_size {
+            4 => (2, 2),
+            8 => (4, 2),
+            16 => (4, 4),
+            n => (n.clamp(1, MAX_PACKET) as u32, 1),
+        }
     }
-    
+
+    // 以光线包追踪左上角为(i, j)、大小为w*h的像素块：每轮把块内各像素的一条主光线打成一包求交，
+    // 其后的弹射方向已不相干，逐条追踪。返回各像素的平均颜色（未乘曝光），按行存放
+    pub fn sample_tile(&self, world: &dyn Hittable, i: u32, j: u32, w: u32, h: u32) -> Vec<Color> {
+        let n = (w * h) as usize;
+        let mut colors = vec![Color::default(); n];
+        if self.max_depth <= 0 {
+            return colors;
+        }
+        let ray_t = Interval::new(0.001, util::INFINITY);
+        for _ in 0..self.samples_per_pixel {
+            let mut rays = Vec::with_capacity(n);
+            let mut active = Vec::with_capacity(n);
+            for y in j..j + h {
+                for x in i..i + w {
+                    let r = self.get_ray(x, y);
+                    active.push(r.is_some());
+                    rays.push(r.unwrap_or_default());
+                }
+            }
+            let mut packet = RayPacket::new(rays, &active, ray_t.clone());
+            world.hit_packet(&mut packet);
+            for (k, color) in colors.iter_mut().enumerate() {
+                if !active[k] {
+                    continue;
+                }
+                let c = if packet.hits[k] {
+                    self.shade(&packet.rays[k], &packet.recs[k], self.max_depth, world)
+                } else {
+                    self.background
+                };
+                *color += c / self.samples_per_pixel as f64;
+            }
+        }
+        colors
+    }
+
+    // 返回像素(i,j)的一条随机采样光线；鱼眼成像圆外的像素没有光线
+    pub fn get_ray(&self, i: u32, j: u32) -> Option<Ray> {
+        let ray_time = self.shutter_open + (self.lens_shutter_close - self.shutter_open) * random_double();
+
+        if self.projection == Projection::Perspective {
+            // Get a randomly sampled camera ray for the pixel at location i,j.
+            let pixel_center = self.pixel00_loc + i as f64 * self.pixel_delta_u + j as f64 * self.pixel_delta_v;
+            let pixel_sample = pixel_center + self.sample_square();
+
+            if self.lens_defocus_angle <= 0.0 {
+                return Some(Ray::new_time(self.center + self.eye_offset * self.u, pixel_sample - self.center, ray_time));
+            }
+
+            let half_diagonal = 0.5 * ((self.image_width as f64).powi(2) + (self.image_height as f64).powi(2)).sqrt();
+            let offset = (
+                (i as f64 + 0.5 - 0.5 * self.image_width as f64) / half_diagonal,
+                (0.5 * self.image_height as f64 - j as f64 - 0.5) / half_diagonal,
+            );
+            let ray_origin = self.defocus_disk_sample(offset)?;
+            // 针孔光线与（可能倾斜的）焦平面的交点，所有透镜位置的光线都汇聚于此
+            let pinhole_dir = pixel_sample - self.center;
+            let focus_point = self.center - self.focus_dist * self.w;
+            let denom = Vec3::dot(pinhole_dir, self.focus_normal);
+            let t = Vec3::dot(focus_point - self.center, self.focus_normal) / denom;
+            let target = if t > 0.0 && t.is_finite() { self.center + t * pinhole_dir } else { pixel_sample };
+            let ray_direction = target - ray_origin;
+            // 双目时整台相机平移，视线方向不变
+            return Some(Ray::new_time(ray_origin + self.eye_offset * self.u, ray_direction, ray_time));
+        }
+
+        // 像素内的采样点归一化到[0,1]
+        let sx = (i as f64 + random_double()) / self.image_width as f64;
+        let sy = (j as f64 + random_double()) / self.image_height as f64;
+        let aspect = self.image_width as f64 / self.image_height as f64;
+
+        match self.projection {
+            Projection::Orthographic { height } => {
+                let origin = self.center
+                    + (sx - 0.5) * height * aspect * self.u
+                    - (sy - 0.5) * height * self.v
+                    + self.eye_offset * self.u;
+                Some(Ray::new_time(origin, -self.w, ray_time))
+            }
+            Projection::Fisheye { fov } => {
+                // 成像圆内切于较短的一边
+                let scale = aspect.max(1.0 / aspect);
+                let (mut x, mut y) = (2.0 * sx - 1.0, 1.0 - 2.0 * sy);
+                if aspect > 1.0 { x *= scale } else { y *= scale }
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+                let theta = r * util::degrees_to_radians(fov) / 2.0;
+                let phi = y.atan2(x);
+                let dir = theta.sin() * (phi.cos() * self.u + phi.sin() * self.v) - theta.cos() * self.w;
+                Some(Ray::new_time(self.center + self.eye_offset * self.u, dir, ray_time))
+            }
+            Projection::Equirectangular => {
+                let phi = (sx - 0.5) * 2.0 * util::PI;
+                let theta = (0.5 - sy) * util::PI;
+                let dir = theta.cos() * phi.sin() * self.u + theta.sin() * self.v - theta.cos() * phi.cos() * self.w;
+                // 全向立体：眼睛位置随水平视线方向旋转
+                let right = phi.cos() * self.u + phi.sin() * self.w;
+                Some(Ray::new_time(self.center + self.eye_offset * right, dir, ray_time))
+            }
+            Projection::Perspective => unreachable!(),
+        }
+    }
+
+    // 左右眼相机，separation为瞳距
+    pub fn stereo_pair(&self, separation: f64) -> (Camera, Camera) {
+        let mut left = self.clone();
+        let mut right = self.clone();
+        left.eye_offset = -0.5 * separation;
+        right.eye_offset = 0.5 * separation;
+        (left, right)
+    }
+
     pub fn render(&mut self, world: &dyn Hittable) {
         self.initialize();
 
@@ -193,9 +459,11 @@ impl Camera {
                 // let color_vec = Self::ray_color(&r,world);
                 let mut color_vec = Vec3::zero();
                 for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    color_vec += Self::ray_color(self,&r,self.max_depth, world)/self.samples_per_pixel as f64;
+                    if let Some(r) = self.get_ray(i, j) {
+                        color_vec += Self::ray_color(self,&r,self.max_depth, world)/self.samples_per_pixel as f64;
+                    }
                 }
+                color_vec *= self.exposure_scale;
                 color_vec.x = linear_to_gamma(color_vec.x);
                 color_vec.y = linear_to_gamma(color_vec.y);
                 color_vec.z = linear_to_gamma(color_vec.z);
@@ -225,4 +493,113 @@ impl Camera {
     
 
     
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_physical_exposure() {
+        use crate::animation::{CameraKey, CameraTrack};
+
+        // 晴天16法则约为EV 14.6
+        let exposure = PhysicalExposure::default();
+        assert!((exposure.ev100() - 25600f64.log2()).abs() < 1e-9);
+
+        // 50mm f/2 的光圈直径为25mm，对焦2米处散焦角约为 2*atan(0.0125/2)
+        let mut cam = Camera {
+            vfov: 2.0 * (12.0f64 / 50.0).atan().to_degrees(),
+            focus_dist: 2.0,
+            shutter_open: 1.0,
+            exposure: Some(PhysicalExposure { f_number: 2.0, ..exposure }),
+            ..Camera::default()
+        };
+        cam.initialize();
+        assert!((cam.lens_defocus_angle - 2.0 * (0.0125f64 / 2.0).atan().to_degrees()).abs() < 1e-9);
+        assert!((cam.lens_shutter_close - 1.01).abs() < 1e-12);
+        // 光圈从f/16开到f/2，亮度为64倍
+        assert!((cam.exposure_scale() / exposure.exposure_scale() - 64.0).abs() < 1e-9);
+
+        // 用户设置不被改写，去掉exposure后重新initialize回到原来的景深和快门
+        assert_eq!((cam.defocus_angle, cam.shutter_close), (0.0, 1.0));
+        cam.exposure = None;
+        cam.initialize();
+        assert_eq!((cam.lens_defocus_angle, cam.lens_shutter_close, cam.exposure_scale()), (0.0, 1.0, 1.0));
+
+        // 关键帧中的散焦角写入defocus_angle后生效
+        let mut key = CameraKey::from_camera(0.0, &cam);
+        key.defocus_angle = 3.0;
+        CameraTrack::new(vec![key]).apply(&mut cam, 0.0);
+        cam.initialize();
+        assert_eq!(cam.lens_defocus_angle, 3.0);
+    }
+
+    #[test]
+    fn test_ambient_occlusion() {
+        use crate::hittable_list::HittableList;
+        use crate::material::Lambertian;
+        use crate::qard::Quad;
+
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let ground: Arc<dyn Hittable> = Arc::new(Quad::new(Point3::new(-10.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 20.0), Vec3::new(20.0, 0.0, 0.0), mat.clone()));
+        let cam = Camera {
+            integrator: Integrator::AmbientOcclusion { samples: 64, distance: 1000.0 },
+            ..Camera::default()
+        };
+        let down = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+
+        // 空旷的地面完全可见
+        let open = HittableList::new(ground.clone());
+        assert_eq!(cam.ray_color(&down, 1, &open), Color::ones());
+
+        // 紧贴地面的大顶板挡住了（几乎）整个半球
+        let mut covered = HittableList::new(ground);
+        covered.add(Arc::new(Quad::new(Point3::new(-500.0, 0.5, -500.0), Vec3::new(1000.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1000.0), mat)));
+        let start = Ray::new(Point3::new(0.0, 0.25, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(cam.ray_color(&start, 1, &covered), Color::zero());
+    }
+
+    // 采样光线在像素内有随机偏移，比较时留一个像素左右的误差
+    fn assert_near(a: Vec3, b: Vec3, tolerance: f64) {
+        assert!((a - b).length() < tolerance, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_projection_directions() {
+        // 相机在原点看向-z，u、v、w即x、y、z轴；图像高度固定为450
+        let mut cam = Camera {
+            lookfrom: Point3::zero(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            image_width: 450,
+            projection: Projection::Orthographic { height: 10.0 },
+            ..Camera::default()
+        };
+        cam.initialize();
+        // 正交投影：方向都是视线方向，起点铺满10x10的视野
+        let r = cam.get_ray(0, 0).unwrap();
+        assert_near(r.dir, Vec3::new(0.0, 0.0, -1.0), 1e-12);
+        assert_near(r.orig, Point3::new(-5.0, 5.0, 0.0), 0.05);
+        let r = cam.get_ray(225, 225).unwrap();
+        assert_near(r.orig, Point3::zero(), 0.05);
+
+        // 180度鱼眼：中心看正前方，左右边缘看正侧面，成像圆外的角落没有光线
+        cam.projection = Projection::Fisheye { fov: 180.0 };
+        cam.initialize();
+        assert_near(Vec3::unit_vector(cam.get_ray(225, 225).unwrap().dir), Vec3::new(0.0, 0.0, -1.0), 0.02);
+        assert_near(Vec3::unit_vector(cam.get_ray(449, 224).unwrap().dir), Vec3::new(1.0, 0.0, 0.0), 0.02);
+        assert_near(Vec3::unit_vector(cam.get_ray(0, 224).unwrap().dir), Vec3::new(-1.0, 0.0, 0.0), 0.02);
+        assert!(cam.get_ray(0, 0).is_none());
+        assert!(cam.get_ray(449, 449).is_none());
+
+        // 等距柱状：2:1的图像，中心为正前方，横向3/4处为右方，顶行为正上方
+        cam.image_width = 900;
+        cam.projection = Projection::Equirectangular;
+        cam.initialize();
+        assert_near(Vec3::unit_vector(cam.get_ray(450, 225).unwrap().dir), Vec3::new(0.0, 0.0, -1.0), 0.02);
+        assert_near(Vec3::unit_vector(cam.get_ray(675, 225).unwrap().dir), Vec3::new(1.0, 0.0, 0.0), 0.02);
+        assert_near(Vec3::unit_vector(cam.get_ray(225, 225).unwrap().dir), Vec3::new(-1.0, 0.0, 0.0), 0.02);
+        assert_near(Vec3::unit_vector(cam.get_ray(450, 0).unwrap().dir), Vec3::new(0.0, 1.0, 0.0), 0.02);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::util;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 胶囊体：线段p0-p1扫过半径为radius的球
+pub struct Capsule {
+    p0: Point3,
+    length: f64,
+    radius: f64,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Capsule {
+    pub fn new(p0: Point3, p1: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vec3::new(radius, radius, radius);
+        let bbox = Aabb::new_box(
+            &Aabb::new_point(&(p0 - rvec), &(p0 + rvec)),
+            &Aabb::new_point(&(p1 - rvec), &(p1 + rvec)),
+        );
+        Self {
+            p0,
+            length: (p1 - p0).length(),
+            radius,
+            onb: Onb::new(p1 - p0),
+            mat,
+            bbox,
+        }
+    }
+
+    // 最近交点的t
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<f64> {
+        // 局部坐标系，轴为z，两端球心分别在z=0和z=length
+        let o = self.onb.to_local(r.orig - self.p0);
+        let d = self.onb.to_local(r.dir);
+        let rr = self.radius * self.radius;
+
+        let mut closest = ray_t.max;
+
+        // 圆柱侧面
+        let a = d.x() * d.x() + d.y() * d.y();
+        if a > 1e-12 {
+            let h = o.x() * d.x() + o.y() * d.y();
+            let c = o.x() * o.x() + o.y() * o.y() - rr;
+            let discriminant = h * h - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                for root in [(-h - sqrtd) / a, (-h + sqrtd) / a] {
+                    let z = o.z() + root * d.z();
+                    if root > ray_t.min && root < closest && (0.0..=self.length).contains(&z) {
+                        closest = root;
+                    }
+                }
+            }
+        }
+
+        // 两端半球，只接受各自外侧的那一半
+        let a = d.squared_length();
+        for (cz, bottom) in [(0.0, true), (self.length, false)] {
+            let oc = Vec3::new(0.0, 0.0, cz) - o;
+            let h = Vec3::dot(d, oc);
+            let c = oc.squared_length() - rr;
+            let discriminant = h * h - a * c;
+            if discriminant < 0.0 {
+                continue;
+            }
+            let sqrtd = discriminant.sqrt();
+            for root in [(h - sqrtd) / a, (h + sqrtd) / a] {
+                let p = o + root * d;
+                let outside = if bottom { p.z() <= 0.0 } else { p.z() >= self.length };
+                if root > ray_t.min && root < closest && outside {
+                    closest = root;
+                }
+            }
+        }
+
+        (closest < ray_t.max).then_some(closest)
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some(closest) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, closest);
+        true
+    }
+
+    // 法线从轴线上最近的点指向局部交点：侧面上垂直于轴，两端为半球的径向
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        rec.p = r.at(rec.t);
+        let p = self.onb.to_local(rec.p - self.p0);
+        let normal = p - Vec3::new(0.0, 0.0, p.z().clamp(0.0, self.length));
+        rec.set_face_normal(r, self.onb.to_world(normal / self.radius));
+        rec.u = (p.y().atan2(p.x()) + util::PI) / (2.0 * util::PI);
+        rec.v = (p.z() + self.radius) / (self.length + 2.0 * self.radius);
+        rec.set_material(&*self.mat);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec3::Color;
+
+    fn hit(object: &dyn Hittable, orig: Point3, dir: Vec3) -> Option<HitRecord<'_>> {
+        let mut rec = HitRecord::default();
+        object.hit(&Ray::new(orig, dir), &Interval::new(0.001, util::INFINITY), &mut rec).then_some(rec)
+    }
+
+    #[test]
+    fn test_hit_side_and_ends() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let capsule = Capsule::new(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 0.5, mat);
+
+        let rec = hit(&capsule, Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-8);
+
+        // 两端的半球
+        let rec = hit(&capsule, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 2.5).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-8);
+        assert!((rec.v - 1.0).abs() < 1e-8);
+        let rec = hit(&capsule, Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-8);
+        assert!(rec.v.abs() < 1e-8);
+        let rec = hit(&capsule, Point3::new(0.3, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 2.6).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.6, 0.8, 0.0)).length() < 1e-8);
+
+        assert!(hit(&capsule, Point3::new(-5.0, 3.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_hit_from_inside() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let capsule = Capsule::new(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 0.5, mat);
+
+        let rec = hit(&capsule, Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-8);
+        assert!(!rec.front_face);
+        // 从半球球心出发，只命中外侧的那一半
+        let rec = hit(&capsule, Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-8);
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-8);
+    }
+}
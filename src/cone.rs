@@ -0,0 +1,217 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::util;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 被击中的部件，存入HitRecord::part
+const SIDE: usize = 0;
+const BOTTOM: usize = 1;
+const TOP: usize = 2;
+
+// 圆锥台，顶面半径为0时即圆锥
+pub struct Cone {
+    base: Point3,
+    height: f64,
+    base_radius: f64,
+    top_radius: f64,
+    slope: f64,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Cone {
+    pub fn new(base: Point3, apex: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Self::new_frustum(base, apex, radius, 0.0, mat)
+    }
+
+    pub fn new_frustum(
+        base: Point3,
+        top: Point3,
+        base_radius: f64,
+        top_radius: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let onb = Onb::new(top - base);
+        let height = (top - base).length();
+        let e0 = onb.disk_extent(base_radius);
+        let e1 = onb.disk_extent(top_radius);
+        let bbox = Aabb::new_box(
+            &Aabb::new_point(&(base - e0), &(base + e0)),
+            &Aabb::new_point(&(top - e1), &(top + e1)),
+        )
+        .pad();
+        Self {
+            base,
+            height,
+            base_radius,
+            top_radius,
+            slope: (top_radius - base_radius) / height,
+            onb,
+            mat,
+            bbox,
+        }
+    }
+
+    fn radius_at(&self, z: f64) -> f64 {
+        self.base_radius + self.slope * z
+    }
+
+    // 最近的交点：t和被击中的部件
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, usize)> {
+        // 局部坐标系，轴为z，底面在z=0；侧面满足 x^2+y^2 = (r0 + s*z)^2
+        let o = self.onb.to_local(r.orig - self.base);
+        let d = self.onb.to_local(r.dir);
+
+        let mut closest = ray_t.max;
+        let mut found = None;
+
+        let e = self.radius_at(o.z());
+        let f = self.slope * d.z();
+        let a = d.x() * d.x() + d.y() * d.y() - f * f;
+        let h = o.x() * d.x() + o.y() * d.y() - e * f;
+        let c = o.x() * o.x() + o.y() * o.y() - e * e;
+
+        // 无解的根保留为NaN，后续比较自然不成立
+        let mut roots = [f64::NAN; 2];
+        if a.abs() > 1e-12 {
+            let discriminant = h * h - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                roots = [(-h - sqrtd) / a, (-h + sqrtd) / a];
+            }
+        } else if h.abs() > 1e-12 {
+            // 光线平行于母线
+            roots[0] = -c / (2.0 * h);
+        }
+
+        for root in roots {
+            let z = o.z() + root * d.z();
+            // 排除另一半镜像锥面
+            if root > ray_t.min && root < closest && (0.0..=self.height).contains(&z) && self.radius_at(z) >= 0.0 {
+                closest = root;
+                found = Some(SIDE);
+            }
+        }
+
+        // 底面与顶面
+        if d.z().abs() > 1e-12 {
+            for (cap_z, cap_r, part) in [(0.0, self.base_radius, BOTTOM), (self.height, self.top_radius, TOP)] {
+                if cap_r <= 0.0 {
+                    continue;
+                }
+                let root = (cap_z - o.z()) / d.z();
+                if root > ray_t.min && root < closest {
+                    let x = o.x() + root * d.x();
+                    let y = o.y() + root * d.y();
+                    if x * x + y * y <= cap_r * cap_r {
+                        closest = root;
+                        found = Some(part);
+                    }
+                }
+            }
+        }
+
+        found.map(|part| (closest, part))
+    }
+}
+
+impl Hittable for Cone {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some((closest, part)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, closest);
+        rec.part = part;
+        true
+    }
+
+    // 由局部坐标系中的交点求法线（侧面未归一化）和纹理坐标
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        rec.p = r.at(rec.t);
+        let p = self.onb.to_local(rec.p - self.base);
+        let (local_normal, u, v) = if rec.part == SIDE {
+            let u = (p.y().atan2(p.x()) + util::PI) / (2.0 * util::PI);
+            (Vec3::new(p.x(), p.y(), -self.slope * self.radius_at(p.z())), u, p.z() / self.height)
+        } else {
+            let (nz, cap_r) = if rec.part == TOP { (1.0, self.top_radius) } else { (-1.0, self.base_radius) };
+            (Vec3::new(0.0, 0.0, nz), 0.5 * (p.x() / cap_r + 1.0), 0.5 * (p.y() / cap_r + 1.0))
+        };
+        rec.set_face_normal(r, Vec3::unit_vector(self.onb.to_world(local_normal)));
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&*self.mat);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec3::Color;
+
+    fn hit(object: &dyn Hittable, orig: Point3, dir: Vec3) -> Option<HitRecord<'_>> {
+        let mut rec = HitRecord::default();
+        object.hit(&Ray::new(orig, dir), &Interval::new(0.001, util::INFINITY), &mut rec).then_some(rec)
+    }
+
+    #[test]
+    fn test_hit_side_and_caps() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cone = Cone::new(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 1.0, mat.clone());
+
+        // 半高处半径0.5，法线沿母线的垂直方向向上倾斜
+        let rec = hit(&cone, Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 4.5).abs() < 1e-8);
+        assert!((rec.normal - Vec3::unit_vector(Vec3::new(-1.0, 0.5, 0.0))).length() < 1e-8);
+        assert!(rec.front_face);
+
+        let rec = hit(&cone, Point3::new(0.2, -5.0, 0.1), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-8);
+
+        // 顶点之上是镜像的另一半锥面，不应命中
+        assert!(hit(&cone, Point3::new(-5.0, 3.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_none());
+
+        // 圆锥台的顶面
+        let frustum = Cone::new_frustum(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 1.0, 0.5, mat);
+        let rec = hit(&frustum, Point3::new(0.1, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-8);
+    }
+
+    #[test]
+    fn test_hit_from_inside() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cone = Cone::new(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 1.0, mat);
+
+        let rec = hit(&cone, Point3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 0.75).abs() < 1e-8);
+        assert!(!rec.front_face);
+        let rec = hit(&cone, Point3::new(0.0, 0.5, 0.0), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 0.5).abs() < 1e-8);
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-8);
+    }
+}
@@ -33,7 +33,7 @@ impl ConstantMedium {
 }
 
 impl Hittable for ConstantMedium {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
         // Print occasional samples when debugging. To enable, set enableDebug true.
         const ENABLE_DEBUG: bool = false;
         let debugging = ENABLE_DEBUG && util::random_double() < 0.00001;
@@ -41,11 +41,12 @@ impl Hittable for ConstantMedium {
         let mut rec1 = HitRecord::default();
         let mut rec2 = HitRecord::default();
 
-        if !self.boundary.hit(r, &Interval::UNIVERSE, &mut rec1) {
+        // 只需要进出边界的t，不必补全边界上的表面信息
+        if !self.boundary.hit_deferred(r, &Interval::UNIVERSE, &mut rec1) {
             return false;
         }
 
-        if !self.boundary.hit(r, &Interval::new(rec1.t + 0.0001, util::INFINITY), &mut rec2) {
+        if !self.boundary.hit_deferred(r, &Interval::new(rec1.t + 0.0001, util::INFINITY), &mut rec2) {
             return false;
         }
 
@@ -87,7 +88,7 @@ impl Hittable for ConstantMedium {
 
         rec.normal = Vec3::new(1.0, 0.0, 0.0); // arbitrary
         rec.front_face = true; // also arbitrary
-        rec.mat = Some(Arc::clone(&self.phase_function));
+        rec.set_material(&*self.phase_function);
 
         true
     }
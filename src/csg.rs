@@ -0,0 +1,344 @@
+use crate::aabb::Aabb;
+use crate::bvh::{BvhNode, FlatBvh};
+use crate::capsule::Capsule;
+use crate::cone::Cone;
+use crate::cylinder::Cylinder;
+use crate::hittable::{HitRecord, Hittable, RotateY, Transform, Translate};
+use crate::hittable_list::HittableList;
+use crate::instance::Instance;
+use crate::interval::Interval;
+use crate::motion::MotionTransform;
+use crate::ray::Ray;
+use crate::torus::Torus;
+use crate::util::INFINITY;
+use crate::wide_bvh::WideBvh;
+use std::sync::Arc;
+
+// 光线位于实体内部的一段，两端记录交点信息，normal为朝外的法线。
+// 光线起点之前就已在内部时 enter.t 为 -INFINITY，反之 exit.t 为 INFINITY。
+#[derive(Clone, Copy)]
+pub struct Span<'a> {
+    pub enter: HitRecord<'a>,
+    pub exit: HitRecord<'a>,
+}
+
+// 封闭的实体：可以求出整条直线上所有位于内部的区间
+pub trait Solid: Hittable {
+    // 按t升序写入out。默认实现沿光线逐个求交，按正反面配对，
+    // 适用于由面片组成的封闭网格（法线须一致朝外）。
+    fn spans<'a>(&'a self, r: &Ray, out: &mut Vec<Span<'a>>) {
+        const MAX_CROSSINGS: usize = 64;
+
+        let mut t_min = -INFINITY;
+        let mut enter: Option<HitRecord> = None;
+        for _ in 0..MAX_CROSSINGS {
+            let mut rec = HitRecord::default();
+            if !self.hit(r, &Interval::new(t_min, INFINITY), &mut rec) {
+                break;
+            }
+            t_min = rec.t + 1e-9 * rec.t.abs().max(1.0);
+            if !rec.front_face {
+                rec.normal = -rec.normal;
+            }
+
+            if rec.front_face {
+                if enter.is_none() {
+                    enter = Some(rec);
+                }
+            } else {
+                let enter = enter.take().unwrap_or_else(|| unbounded_record(-INFINITY));
+                out.push(Span { enter, exit: rec });
+            }
+        }
+        if let Some(enter) = enter {
+            out.push(Span { enter, exit: unbounded_record(INFINITY) });
+        }
+    }
+}
+
+fn unbounded_record(t: f64) -> HitRecord<'static> {
+    HitRecord {
+        t,
+        ..Default::default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+pub struct Csg {
+    left: Arc<dyn Solid>,
+    right: Arc<dyn Solid>,
+    op: CsgOp,
+    bbox: Aabb,
+}
+
+impl Csg {
+    pub fn new(left: Arc<dyn Solid>, right: Arc<dyn Solid>, op: CsgOp) -> Self {
+        let (a, b) = (left.bounding_box(), right.bounding_box());
+        let bbox = match op {
+            CsgOp::Union => Aabb::new_box(a, b),
+            CsgOp::Intersection => Aabb::new(
+                &Interval::new(a.x.min.max(b.x.min), a.x.max.min(b.x.max)),
+                &Interval::new(a.y.min.max(b.y.min), a.y.max.min(b.y.max)),
+                &Interval::new(a.z.min.max(b.z.min), a.z.max.min(b.z.max)),
+            ),
+            CsgOp::Difference => a.clone(),
+        };
+        Self { left, right, op, bbox }
+    }
+
+    pub fn union(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self::new(left, right, CsgOp::Union)
+    }
+
+    pub fn intersection(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self::new(left, right, CsgOp::Intersection)
+    }
+
+    pub fn difference(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self::new(left, right, CsgOp::Difference)
+    }
+}
+
+impl Solid for Csg {
+    fn spans<'a>(&'a self, r: &Ray, out: &mut Vec<Span<'a>>) {
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        self.left.spans(r, &mut a);
+        if a.is_empty() && self.op != CsgOp::Union {
+            return;
+        }
+        self.right.spans(r, &mut b);
+
+        // 所有端点按t排序后扫描，记录组合结果内外状态变化的位置
+        let mut events: Vec<(&HitRecord, bool, bool)> = Vec::with_capacity(2 * (a.len() + b.len()));
+        for span in a.iter() {
+            events.push((&span.enter, true, true));
+            events.push((&span.exit, true, false));
+        }
+        for span in b.iter() {
+            events.push((&span.enter, false, true));
+            events.push((&span.exit, false, false));
+        }
+        events.sort_by(|x, y| x.0.t.total_cmp(&y.0.t));
+
+        let (mut in_a, mut in_b, mut inside) = (false, false, false);
+        let mut enter: Option<HitRecord> = None;
+        for (rec, from_a, entering) in events {
+            if from_a {
+                in_a = entering;
+            } else {
+                in_b = entering;
+            }
+            let now = self.op.inside(in_a, in_b);
+            if now == inside {
+                continue;
+            }
+            inside = now;
+
+            // 进入结果却是离开子实体（或反之）时，法线需要反向
+            let mut rec = *rec;
+            if entering != now {
+                rec.normal = -rec.normal;
+            }
+            if now {
+                enter = Some(rec);
+            } else {
+                let enter = enter.take().unwrap_or_else(|| unbounded_record(-INFINITY));
+                out.push(Span { enter, exit: rec });
+            }
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let mut box_t = ray_t.clone();
+        if !self.bbox.hit(r, &mut box_t) {
+            return false;
+        }
+
+        let mut spans = Vec::new();
+        self.spans(r, &mut spans);
+        let boundary = spans
+            .iter()
+            .flat_map(|span| [&span.enter, &span.exit])
+            .find(|boundary| ray_t.surrounds(boundary.t));
+        let Some(boundary) = boundary else {
+            return false;
+        };
+
+        let outward_normal = boundary.normal;
+        *rec = *boundary;
+        rec.set_face_normal(r, outward_normal);
+
+        true
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        if !self.bbox.hit(r, &mut ray_t.clone()) {
+            return false;
+        }
+        let mut spans = Vec::new();
+        self.spans(r, &mut spans);
+        spans.iter().any(|span| ray_t.surrounds(span.enter.t) || ray_t.surrounds(span.exit.t))
+    }
+}
+
+// 以下封闭物体沿用默认的逐个求交实现
+impl Solid for HittableList {}
+impl Solid for Translate {}
+impl Solid for RotateY {}
+impl Solid for Transform {}
+impl Solid for Instance {}
+impl Solid for Cylinder {}
+impl Solid for Cone {}
+impl Solid for Torus {}
+impl Solid for Capsule {}
+impl Solid for MotionTransform {}
+// 三角网格放进BVH后作为实体使用，要求网格封闭且法线一致朝外，见TriangleMesh::into_solid
+impl Solid for BvhNode {}
+impl Solid for FlatBvh {}
+impl<const N: usize> Solid for WideBvh<N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::mesh::TriangleMesh;
+    use crate::qard::box_sides;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn mat() -> Arc<Lambertian> {
+        Arc::new(Lambertian::new(Color::ones()))
+    }
+
+    fn cube() -> Arc<dyn Solid> {
+        Arc::new(box_sides(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0), mat()))
+    }
+
+    fn ball(x: f64, radius: f64) -> Arc<dyn Solid> {
+        Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), radius, mat()))
+    }
+
+    // 沿x轴穿过的光线得到的区间端点
+    fn spans_along_x(solid: &dyn Solid, orig_x: f64) -> Vec<(f64, f64)> {
+        let r = Ray::new(Point3::new(orig_x, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut spans = Vec::new();
+        solid.spans(&r, &mut spans);
+        spans.iter().map(|span| (span.enter.t + orig_x, span.exit.t + orig_x)).collect()
+    }
+
+    fn assert_spans(actual: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a.0 - e.0).abs() < 1e-9 && (a.1 - e.1).abs() < 1e-9, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_boolean_ops() {
+        // 盒子减去球心在原点的球，沿x轴分成两段，空洞内壁的法线朝向空洞
+        let carved = Csg::difference(cube(), ball(0.0, 0.5));
+        assert_spans(&spans_along_x(&carved, -5.0), &[(-1.0, -0.5), (0.5, 1.0)]);
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut spans = Vec::new();
+        carved.spans(&r, &mut spans);
+        assert!((spans[0].exit.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((spans[1].enter.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+
+        // 两个重叠的球：并集合并成一段，交集只剩重叠部分
+        assert_spans(&spans_along_x(&Csg::union(ball(-0.5, 1.0), ball(0.5, 1.0)), -5.0), &[(-1.5, 1.5)]);
+        assert_spans(&spans_along_x(&Csg::intersection(ball(-0.5, 1.0), ball(0.5, 1.0)), -5.0), &[(-0.5, 0.5)]);
+        assert_spans(&spans_along_x(&Csg::difference(ball(-0.5, 1.0), ball(0.5, 1.0)), -5.0), &[(-1.5, -0.5)]);
+
+        // 不相交的两个球：并集保持两段，交集为空
+        assert_spans(&spans_along_x(&Csg::union(ball(-2.0, 1.0), ball(2.0, 1.0)), -5.0), &[(-3.0, -1.0), (1.0, 3.0)]);
+        assert_spans(&spans_along_x(&Csg::intersection(ball(-2.0, 1.0), ball(2.0, 1.0)), -5.0), &[]);
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!Csg::intersection(ball(-2.0, 1.0), ball(2.0, 1.0)).hit(&r, &Interval::new(0.001, INFINITY), &mut HitRecord::default()));
+    }
+
+    #[test]
+    fn test_ray_starting_inside() {
+        let carved = Csg::difference(cube(), ball(0.0, 0.5));
+        let ray_t = Interval::new(0.001, INFINITY);
+
+        // 起点在实体内：向外命中盒子表面，向内命中空洞内壁，都是从背面离开实体
+        let mut rec = HitRecord::default();
+        let r = Ray::new(Point3::new(0.75, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(carved.hit(&r, &ray_t, &mut rec));
+        assert!((rec.t - 0.25).abs() < 1e-9);
+        assert!(!rec.front_face);
+
+        let r = Ray::new(Point3::new(0.75, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        assert!(carved.hit(&r, &ray_t, &mut rec));
+        assert!((rec.t - 0.25).abs() < 1e-9);
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+
+        // 起点在空洞里：先从正面进入实体
+        let r = Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0));
+        assert!(carved.hit(&r, &ray_t, &mut rec));
+        assert!((rec.t - 0.5).abs() < 1e-9);
+        assert!(rec.front_face);
+        // 区间覆盖整条直线，与起点位置无关
+        assert_spans(&spans_along_x(&carved, 0.75), &[(-1.0, -0.5), (0.5, 1.0)]);
+    }
+
+    #[test]
+    fn test_mesh_solid_matches_box() {
+        use crate::util::random_double_range;
+
+        // 12个三角形拼成的立方体网格，各面按朝外的方向排列顶点
+        let positions: Vec<Point3> = (0..8)
+            .map(|i| Point3::new(if i & 1 != 0 { 1.0 } else { -1.0 }, if i & 2 != 0 { 1.0 } else { -1.0 }, if i & 4 != 0 { 1.0 } else { -1.0 }))
+            .collect();
+        let mut indices = Vec::new();
+        for axis in 0..3 {
+            for side in [0, 1] {
+                let k: Vec<usize> = (0..8).filter(|i| (i >> axis) & 1 == side).collect();
+                for [a, b, c] in [[k[0], k[1], k[3]], [k[0], k[3], k[2]]] {
+                    let n = Vec3::cross(positions[b] - positions[a], positions[c] - positions[a]);
+                    indices.push(if Vec3::dot(n, positions[a]) > 0.0 { [a, b, c] } else { [a, c, b] });
+                }
+            }
+        }
+        let mesh = TriangleMesh::new(positions, indices).into_solid(mat());
+        let from_mesh = Csg::difference(mesh, ball(0.0, 0.8));
+        let from_box = Csg::difference(cube(), ball(0.0, 0.8));
+
+        let ray_t = Interval::new(0.001, INFINITY);
+        for _ in 0..500 {
+            let r = Ray::new(Point3::random_range(-3.0, 3.0), Vec3::new(random_double_range(-1.0, 1.0), random_double_range(-1.0, 1.0), random_double_range(-1.0, 1.0)));
+            let (mut a, mut b) = (HitRecord::default(), HitRecord::default());
+            let hit = from_box.hit(&r, &ray_t, &mut a);
+            assert_eq!(from_mesh.hit(&r, &ray_t, &mut b), hit);
+            if hit {
+                assert!((a.t - b.t).abs() < 1e-9);
+                assert!((a.normal - b.normal).length() < 1e-9);
+                assert_eq!(a.front_face, b.front_face);
+            }
+        }
+    }
+}
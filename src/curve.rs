@@ -0,0 +1,417 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 曲线的截面形状
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CurveType {
+    // 始终朝向光线的平带
+    Flat,
+    // 平带，但法线按圆柱截面弯曲，看起来像细圆管
+    Cylinder,
+    // 法线由两端给定并插值的带子，适合草叶
+    Ribbon { n0: Vec3, n1: Vec3 },
+}
+
+fn lerp(t: f64, a: Vec3, b: Vec3) -> Vec3 {
+    (1.0 - t) * a + t * b
+}
+
+fn eval_bezier(cp: &[Point3; 4], u: f64) -> (Point3, Vec3) {
+    let cp1 = [lerp(u, cp[0], cp[1]), lerp(u, cp[1], cp[2]), lerp(u, cp[2], cp[3])];
+    let cp2 = [lerp(u, cp1[0], cp1[1]), lerp(u, cp1[1], cp1[2])];
+    let deriv = if (cp2[1] - cp2[0]).near_zero() {
+        // 端点处控制点重合时导数退化，用首末控制点的方向代替
+        cp[3] - cp[0]
+    } else {
+        3.0 * (cp2[1] - cp2[0])
+    };
+    (lerp(u, cp2[0], cp2[1]), deriv)
+}
+
+// 在[u0, u1]上截取的子曲线的控制点（blossom）
+fn blossom(cp: &[Point3; 4], u0: f64, u1: f64, u2: f64) -> Point3 {
+    let a = [lerp(u0, cp[0], cp[1]), lerp(u0, cp[1], cp[2]), lerp(u0, cp[2], cp[3])];
+    let b = [lerp(u1, a[0], a[1]), lerp(u1, a[1], a[2])];
+    lerp(u2, b[0], b[1])
+}
+
+fn sub_curve(cp: &[Point3; 4], u0: f64, u1: f64) -> [Point3; 4] {
+    [
+        blossom(cp, u0, u0, u0),
+        blossom(cp, u0, u0, u1),
+        blossom(cp, u0, u1, u1),
+        blossom(cp, u1, u1, u1),
+    ]
+}
+
+// 在中点处一分为二，共享中间控制点
+fn subdivide(cp: &[Point3; 4]) -> [Point3; 7] {
+    let m01 = 0.5 * (cp[0] + cp[1]);
+    let m12 = 0.5 * (cp[1] + cp[2]);
+    let m23 = 0.5 * (cp[2] + cp[3]);
+    let a = 0.5 * (m01 + m12);
+    let b = 0.5 * (m12 + m23);
+    let mid = 0.5 * (a + b);
+    [cp[0], m01, a, mid, b, m23, cp[3]]
+}
+
+// 同一条曲线被拆成多段时共享的数据
+struct CurveCommon {
+    cp: [Point3; 4],
+    width: [f64; 2],
+    kind: CurveType,
+    normal_angle: f64,
+}
+
+// 三次Bézier曲线，宽度沿曲线线性变化
+pub struct Curve {
+    common: Arc<CurveCommon>,
+    u_min: f64,
+    u_max: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Curve {
+    pub fn new(cp: [Point3; 4], width0: f64, width1: f64, kind: CurveType, mat: Arc<dyn Material>) -> Self {
+        Self::new_segment(Self::common(cp, width0, width1, kind), 0.0, 1.0, mat)
+    }
+
+    // 拆成若干段分别放进BVH，长曲线的包围盒更紧
+    pub fn new_split(
+        cp: [Point3; 4],
+        width0: f64,
+        width1: f64,
+        kind: CurveType,
+        segments: usize,
+        mat: Arc<dyn Material>,
+    ) -> HittableList {
+        let common = Self::common(cp, width0, width1, kind);
+        let segments = segments.max(1);
+        let mut list = HittableList::default();
+        for i in 0..segments {
+            let u0 = i as f64 / segments as f64;
+            let u1 = (i + 1) as f64 / segments as f64;
+            list.add(Arc::new(Self::new_segment(Arc::clone(&common), u0, u1, Arc::clone(&mat))));
+        }
+        list
+    }
+
+    fn common(cp: [Point3; 4], width0: f64, width1: f64, kind: CurveType) -> Arc<CurveCommon> {
+        let kind = match kind {
+            CurveType::Ribbon { n0, n1 } => CurveType::Ribbon {
+                n0: Vec3::unit_vector(n0),
+                n1: Vec3::unit_vector(n1),
+            },
+            k => k,
+        };
+        let normal_angle = match kind {
+            CurveType::Ribbon { n0, n1 } => Vec3::dot(n0, n1).clamp(-1.0, 1.0).acos(),
+            _ => 0.0,
+        };
+        Arc::new(CurveCommon {
+            cp,
+            width: [width0, width1],
+            kind,
+            normal_angle,
+        })
+    }
+
+    fn new_segment(common: Arc<CurveCommon>, u_min: f64, u_max: f64, mat: Arc<dyn Material>) -> Self {
+        let cp = sub_curve(&common.cp, u_min, u_max);
+        let half_width = 0.5 * Self::width_at(&common, u_min).max(Self::width_at(&common, u_max));
+        let mut bbox = Aabb::new_point(&cp[0], &cp[1]);
+        bbox = Aabb::new_box(&bbox, &Aabb::new_point(&cp[2], &cp[3]));
+        let pad = Vec3::new(half_width, half_width, half_width);
+        let min = Point3::new(bbox.x.min, bbox.y.min, bbox.z.min) - pad;
+        let max = Point3::new(bbox.x.max, bbox.y.max, bbox.z.max) + pad;
+        let bbox = Aabb::new_point(&min, &max);
+        Self {
+            common,
+            u_min,
+            u_max,
+            mat,
+            bbox,
+        }
+    }
+
+    fn width_at(common: &CurveCommon, u: f64) -> f64 {
+        (1.0 - u) * common.width[0] + u * common.width[1]
+    }
+
+    // 两端法线之间的球面插值
+    fn ribbon_normal(&self, u: f64) -> Vec3 {
+        match self.common.kind {
+            CurveType::Ribbon { n0, n1 } => {
+                let angle = self.common.normal_angle;
+                if angle < 1e-6 {
+                    return n0;
+                }
+                let inv_sin = 1.0 / angle.sin();
+                ((1.0 - u) * angle).sin() * inv_sin * n0 + (u * angle).sin() * inv_sin * n1
+            }
+            _ => Vec3::zero(),
+        }
+    }
+
+    // 变换到光线空间后开始递归，rec为None时只判断遮挡
+    fn trace<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: Option<&mut HitRecord<'a>>) -> bool {
+        let dir_length = r.dir.length();
+        let frame = Onb::new(r.dir);
+        let to_ray = |p: Point3| frame.to_local(p - r.orig);
+        let cp_world = sub_curve(&self.common.cp, self.u_min, self.u_max);
+        let cp = cp_world.map(to_ray);
+
+        // 细分层数：使线段近似误差小于宽度的5%
+        let l0 = (0..2)
+            .map(|i| {
+                let d = cp[i] - 2.0 * cp[i + 1] + cp[i + 2];
+                d.x().abs().max(d.y().abs()).max(d.z().abs())
+            })
+            .fold(0.0, f64::max);
+        let eps = self.common.width[0].max(self.common.width[1]) * 0.05;
+        let max_depth = if l0 > 0.0 && eps > 0.0 {
+            ((std::f64::consts::SQRT_2 * 6.0 * l0 / (8.0 * eps)).log2() / 2.0).clamp(0.0, 10.0) as u32
+        } else {
+            0
+        };
+
+        let mut t = ray_t.clone();
+        self.recursive_hit(r, &frame, dir_length, &cp, self.u_min, self.u_max, max_depth, &mut t, rec)
+    }
+
+    // 光线空间中递归二分曲线（PBRT的做法）：光线沿+z，原点在(0,0,0)，
+    // depth为0时把曲线段近似为直线段求最近点；rec为None时只判断遮挡，找到任意交点即返回
+    #[allow(clippy::too_many_arguments)]
+    fn recursive_hit<'a>(
+        &'a self,
+        r: &Ray,
+        frame: &Onb,
+        dir_length: f64,
+        cp: &[Point3; 4],
+        u0: f64,
+        u1: f64,
+        depth: u32,
+        ray_t: &mut Interval,
+        mut rec: Option<&mut HitRecord<'a>>,
+    ) -> bool {
+        if depth > 0 {
+            let split = subdivide(cp);
+            let us = [u0, 0.5 * (u0 + u1), u1];
+            let mut hit_anything = false;
+            for seg in 0..2 {
+                let cps = [split[3 * seg], split[3 * seg + 1], split[3 * seg + 2], split[3 * seg + 3]];
+                let half_width = 0.5
+                    * Self::width_at(&self.common, us[seg]).max(Self::width_at(&self.common, us[seg + 1]));
+                let culled = (0..3).any(|axis| {
+                    let min = cps.iter().map(|p| p[axis]).fold(f64::INFINITY, f64::min) - half_width;
+                    let max = cps.iter().map(|p| p[axis]).fold(f64::NEG_INFINITY, f64::max) + half_width;
+                    if axis < 2 {
+                        min > 0.0 || max < 0.0
+                    } else {
+                        min > ray_t.max * dir_length || max < ray_t.min * dir_length
+                    }
+                });
+                if culled {
+                    continue;
+                }
+                if self.recursive_hit(r, frame, dir_length, &cps, us[seg], us[seg + 1], depth - 1, ray_t, rec.as_deref_mut()) {
+                    if rec.is_none() {
+                        return true;
+                    }
+                    hit_anything = true;
+                }
+            }
+            return hit_anything;
+        }
+
+        // 光线需落在两端切线的垂直平面之间
+        let edge = (cp[1].y() - cp[0].y()) * -cp[0].y() + cp[0].x() * (cp[0].x() - cp[1].x());
+        if edge < 0.0 {
+            return false;
+        }
+        let edge = (cp[2].y() - cp[3].y()) * -cp[3].y() + cp[3].x() * (cp[3].x() - cp[2].x());
+        if edge < 0.0 {
+            return false;
+        }
+
+        let (sx, sy) = (cp[3].x() - cp[0].x(), cp[3].y() - cp[0].y());
+        let denom = sx * sx + sy * sy;
+        if denom == 0.0 {
+            return false;
+        }
+        let w = ((-cp[0].x()) * sx + (-cp[0].y()) * sy) / denom;
+        let u = ((1.0 - w) * u0 + w * u1).clamp(u0, u1);
+
+        let mut hit_width = Self::width_at(&self.common, u);
+        let ribbon_n = self.ribbon_normal(u);
+        if let CurveType::Ribbon { .. } = self.common.kind {
+            // 带子侧对光线时变窄
+            hit_width *= Vec3::dot(ribbon_n, frame.w).abs();
+        }
+
+        let (pc, dpcdw) = eval_bezier(cp, w.clamp(0.0, 1.0));
+        let dist2 = pc.x() * pc.x() + pc.y() * pc.y();
+        if dist2 > hit_width * hit_width * 0.25 {
+            return false;
+        }
+        let t = pc.z() / dir_length;
+        if !ray_t.surrounds(t) {
+            return false;
+        }
+        let Some(rec) = rec else {
+            return true;
+        };
+
+        let dist = dist2.sqrt();
+        let edge_func = dpcdw.x() * -pc.y() + pc.x() * dpcdw.y();
+        let v = if edge_func > 0.0 {
+            0.5 + dist / hit_width
+        } else {
+            0.5 - dist / hit_width
+        };
+
+        // 世界空间的切线
+        let (_, dpdu) = eval_bezier(&self.common.cp, u);
+        let tangent = Vec3::unit_vector(dpdu);
+        let view = -frame.w;
+        let normal = match self.common.kind {
+            CurveType::Ribbon { .. } => ribbon_n,
+            kind => {
+                // 朝向光线、垂直于切线的方向
+                let facing = view - Vec3::dot(view, tangent) * tangent;
+                let facing = if facing.near_zero() {
+                    Onb::new(tangent).u
+                } else {
+                    Vec3::unit_vector(facing)
+                };
+                if kind == CurveType::Cylinder && dist > 0.0 {
+                    // 按圆截面，命中点偏离中心越远法线越偏向侧面
+                    let offset = frame.to_world(Vec3::new(-pc.x(), -pc.y(), 0.0));
+                    let side = offset - Vec3::dot(offset, tangent) * tangent - Vec3::dot(offset, facing) * facing;
+                    if side.near_zero() {
+                        facing
+                    } else {
+                        let s = (2.0 * dist / hit_width).min(1.0);
+                        Vec3::unit_vector((1.0 - s * s).sqrt() * facing + s * Vec3::unit_vector(side))
+                    }
+                } else {
+                    facing
+                }
+            }
+        };
+
+        ray_t.max = t;
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.set_face_normal(r, normal);
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&*self.mat);
+        rec.tangent = tangent;
+        true
+    }
+}
+
+impl Hittable for Curve {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        self.trace(r, ray_t, Some(rec))
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.trace(r, ray_t, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::RotateY;
+    use crate::material::Lambertian;
+    use crate::util;
+    use crate::vec3::Color;
+
+    fn straight(kind: CurveType) -> Curve {
+        let cp = [
+            Point3::new(-1.0, 0.0, 0.0),
+            Point3::new(-1.0 / 3.0, 0.0, 0.0),
+            Point3::new(1.0 / 3.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+        ];
+        Curve::new(cp, 0.2, 0.2, kind, Arc::new(Lambertian::new(Color::ones())))
+    }
+
+    #[test]
+    fn test_hit_straight_curve() {
+        let curve = straight(CurveType::Cylinder);
+        let mut rec = HitRecord::default();
+
+        let r = Ray::new(Point3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        assert!(curve.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 2.5).abs() < 1e-8);
+        assert!((rec.u - 0.75).abs() < 1e-6);
+        assert!((rec.v - 0.5).abs() < 1e-6);
+        assert!((rec.tangent - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-8);
+
+        // 曲线直接填写记录，外层的旋转在finish中作用于交点、法线和切线
+        let rotated = RotateY::new(Arc::new(straight(CurveType::Cylinder)), 90.0);
+        let r = Ray::new(Point3::new(5.0, 0.0, -0.5), Vec3::new(-2.0, 0.0, 0.0));
+        assert!(rotated.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 2.5).abs() < 1e-8);
+        assert!((rec.p - Point3::new(0.0, 0.0, -0.5)).length() < 1e-8);
+        assert!((rec.tangent - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-8);
+        assert!((rec.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-8);
+
+        // 偏离中心时圆柱法线向侧面倾斜
+        let r = Ray::new(Point3::new(0.5, 0.05, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(curve.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!(rec.normal.y() > 0.4);
+
+        let r = Ray::new(Point3::new(0.5, 0.15, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!curve.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_ribbon_edge_on() {
+        // 带子法线与光线垂直时看不到
+        let curve = straight(CurveType::Ribbon { n0: Vec3::new(0.0, 1.0, 0.0), n1: Vec3::new(0.0, 1.0, 0.0) });
+        let mut rec = HitRecord::default();
+        let r = Ray::new(Point3::new(0.5, 0.05, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!curve.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        let r = Ray::new(Point3::new(0.5, 5.0, 0.05), Vec3::new(0.0, -1.0, 0.0));
+        assert!(curve.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 5.0).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_closer_hit_clears_tangent() {
+        use crate::bvh::FlatBvh;
+        use crate::sphere::Sphere;
+
+        // 先命中较远的曲线，再命中较近的球，记录里不能留下曲线的切线
+        let mut list = HittableList::default();
+        list.add(Arc::new(straight(CurveType::Cylinder)));
+        list.add(Arc::new(Sphere::new(Point3::new(0.5, 0.0, 2.0), 0.5, Arc::new(Lambertian::new(Color::ones())))));
+        let flat = FlatBvh::new(&list);
+
+        let r = Ray::new(Point3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        for world in [&list as &dyn Hittable, &flat] {
+            let mut rec = HitRecord::default();
+            assert!(world.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+            assert!((rec.t - 2.5).abs() < 1e-8);
+            assert!(rec.tangent.near_zero());
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::util;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 被击中的部件，存入HitRecord::part
+const SIDE: usize = 0;
+const BOTTOM: usize = 1;
+const TOP: usize = 2;
+
+pub struct Cylinder {
+    base: Point3,
+    height: f64,
+    radius: f64,
+    capped: bool,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Cylinder {
+    // 底面圆心base，顶面圆心top
+    pub fn new(base: Point3, top: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Self::build(base, top, radius, true, mat)
+    }
+
+    // 两端不封口的圆筒
+    pub fn new_open(base: Point3, top: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Self::build(base, top, radius, false, mat)
+    }
+
+    fn build(base: Point3, top: Point3, radius: f64, capped: bool, mat: Arc<dyn Material>) -> Self {
+        let onb = Onb::new(top - base);
+        let e = onb.disk_extent(radius);
+        let bbox = Aabb::new_box(
+            &Aabb::new_point(&(base - e), &(base + e)),
+            &Aabb::new_point(&(top - e), &(top + e)),
+        )
+        .pad();
+        Self {
+            base,
+            height: (top - base).length(),
+            radius,
+            capped,
+            onb,
+            mat,
+            bbox,
+        }
+    }
+
+    // 最近的交点：t和被击中的部件
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, usize)> {
+        // 在局部坐标系中求交，轴为z，底面在z=0
+        let o = self.onb.to_local(r.orig - self.base);
+        let d = self.onb.to_local(r.dir);
+
+        let mut closest = ray_t.max;
+        let mut found = None;
+
+        // 侧面
+        let a = d.x() * d.x() + d.y() * d.y();
+        if a > 1e-12 {
+            let h = o.x() * d.x() + o.y() * d.y();
+            let c = o.x() * o.x() + o.y() * o.y() - self.radius * self.radius;
+            let discriminant = h * h - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                for root in [(-h - sqrtd) / a, (-h + sqrtd) / a] {
+                    let z = o.z() + root * d.z();
+                    if root > ray_t.min && root < closest && (0.0..=self.height).contains(&z) {
+                        closest = root;
+                        found = Some(SIDE);
+                    }
+                }
+            }
+        }
+
+        // 底面与顶面
+        if self.capped && d.z().abs() > 1e-12 {
+            for (cap_z, part) in [(0.0, BOTTOM), (self.height, TOP)] {
+                let root = (cap_z - o.z()) / d.z();
+                if root > ray_t.min && root < closest {
+                    let x = o.x() + root * d.x();
+                    let y = o.y() + root * d.y();
+                    if x * x + y * y <= self.radius * self.radius {
+                        closest = root;
+                        found = Some(part);
+                    }
+                }
+            }
+        }
+
+        found.map(|part| (closest, part))
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some((closest, part)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, closest);
+        rec.part = part;
+        true
+    }
+
+    // 由局部坐标系中的交点求法线和纹理坐标
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        rec.p = r.at(rec.t);
+        let p = self.onb.to_local(rec.p - self.base);
+        let (local_normal, u, v) = if rec.part == SIDE {
+            let u = (p.y().atan2(p.x()) + util::PI) / (2.0 * util::PI);
+            (Vec3::new(p.x(), p.y(), 0.0) / self.radius, u, p.z() / self.height)
+        } else {
+            let nz = if rec.part == TOP { 1.0 } else { -1.0 };
+            (Vec3::new(0.0, 0.0, nz), 0.5 * (p.x() / self.radius + 1.0), 0.5 * (p.y() / self.radius + 1.0))
+        };
+        rec.set_face_normal(r, self.onb.to_world(local_normal));
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&*self.mat);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec3::Color;
+
+    fn hit(object: &dyn Hittable, orig: Point3, dir: Vec3) -> Option<HitRecord<'_>> {
+        let mut rec = HitRecord::default();
+        object.hit(&Ray::new(orig, dir), &Interval::new(0.001, util::INFINITY), &mut rec).then_some(rec)
+    }
+
+    #[test]
+    fn test_hit_side_and_caps() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cylinder = Cylinder::new(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 1.0, mat);
+
+        let rec = hit(&cylinder, Point3::new(-5.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-8);
+        assert!(rec.front_face);
+        assert!((rec.v - 0.5).abs() < 1e-8);
+
+        // 顶面和底面
+        let rec = hit(&cylinder, Point3::new(0.3, 5.0, 0.2), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 3.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-8);
+        let rec = hit(&cylinder, Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 5.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-8);
+
+        // 从上方越过顶面
+        assert!(hit(&cylinder, Point3::new(-5.0, 3.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_hit_from_inside() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cylinder = Cylinder::new(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 1.0, mat.clone());
+
+        // 从内部击中侧面和顶面，都是背面，法线朝向光线起点一侧
+        let rec = hit(&cylinder, Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-8);
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-8);
+        let rec = hit(&cylinder, Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-8);
+        assert!(!rec.front_face);
+
+        // 不封口的圆筒：沿轴线穿过，斜着进入时击中内壁
+        let tube = Cylinder::new_open(Point3::zero(), Point3::new(0.0, 2.0, 0.0), 1.0, mat);
+        assert!(hit(&tube, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+        let rec = hit(&tube, Point3::new(0.0, 5.0, 0.0), Vec3::new(0.25, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-8);
+        assert!(!rec.front_face);
+    }
+}
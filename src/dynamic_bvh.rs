@@ -0,0 +1,440 @@
+use crate::aabb::Aabb;
+use crate::bvh::{assert_bounded, build_prims, BuildPrim, BvhNode, TRAVERSAL_COST};
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::ray::Ray;
+use std::sync::Arc;
+
+const NONE: u32 = u32::MAX;
+
+// 动态BVH中物体的句柄，删除前一直有效；删除后句柄可能被新插入的物体复用
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObjectId(u32);
+
+// 叶子时object为物体句柄，left、right为NONE
+struct DynNode {
+    bbox: Aabb,
+    parent: u32,
+    left: u32,
+    right: u32,
+    object: u32,
+}
+
+impl DynNode {
+    fn is_leaf(&self) -> bool {
+        self.left == NONE
+    }
+}
+
+// 可增量更新的BVH，用于动画和交互：每个叶子一个物体，节点带父指针。
+// 插入时自顶向下选代价最小的兄弟节点，删除时用兄弟节点顶替父节点，两者都只更新祖先的包围盒；
+// 物体移动后用update替换，再调用refit自底向上重算包围盒。
+// 包围盒变形会让树的SAH代价上升，超过上次重建时的rebuild_ratio倍时refit自动按SAH整体重建。
+// 物体须有界，无界平面等放在外层的HittableList里
+pub struct DynamicBvh {
+    nodes: Vec<DynNode>,
+    free_nodes: Vec<u32>,
+    objects: Vec<Option<Arc<dyn Hittable>>>,
+    leaf_of: Vec<u32>,
+    free_objects: Vec<u32>,
+    root: u32,
+    bbox: Aabb,
+    built_cost: f64,
+    pub rebuild_ratio: f64,
+}
+
+impl Default for DynamicBvh {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            objects: Vec::new(),
+            leaf_of: Vec::new(),
+            free_objects: Vec::new(),
+            root: NONE,
+            bbox: Aabb::default(),
+            built_cost: 0.0,
+            rebuild_ratio: 1.5,
+        }
+    }
+}
+
+impl DynamicBvh {
+    // 一次性按SAH构建，返回的句柄与list中的顺序一致
+    pub fn new(list: &HittableList) -> (Self, Vec<ObjectId>) {
+        let mut bvh = Self::default();
+        let ids = list.objects.iter().map(|object| bvh.add_slot(Arc::clone(object))).collect();
+        bvh.rebuild();
+        (bvh, ids)
+    }
+
+    pub fn insert(&mut self, object: Arc<dyn Hittable>) -> ObjectId {
+        let id = self.add_slot(object);
+        let leaf = self.new_leaf(id.0);
+        self.insert_leaf(leaf);
+        id
+    }
+
+    pub fn remove(&mut self, id: ObjectId) -> Option<Arc<dyn Hittable>> {
+        let object = self.objects.get_mut(id.0 as usize)?.take()?;
+        let leaf = self.leaf_of[id.0 as usize];
+        self.remove_leaf(leaf);
+        self.free_nodes.push(leaf);
+        self.free_objects.push(id.0);
+        Some(object)
+    }
+
+    // 替换物体（通常是移动后的同一物体），包围盒在下一次refit时更新
+    pub fn update(&mut self, id: ObjectId, object: Arc<dyn Hittable>) {
+        let slot = &mut self.objects[id.0 as usize];
+        assert!(slot.is_some(), "updating a removed object");
+        *slot = Some(object);
+    }
+
+    // 自底向上重算所有包围盒；树的代价退化过多时整体重建，返回是否重建
+    pub fn refit(&mut self) -> bool {
+        if self.root != NONE {
+            self.refit_node(self.root);
+            self.bbox = self.nodes[self.root as usize].bbox.clone();
+        }
+        // 只经过insert、从未整体重建的树没有基准代价，以第一次refit时的代价为基准
+        if self.built_cost == 0.0 {
+            self.built_cost = self.cost();
+            return false;
+        }
+        if self.cost() > self.built_cost * self.rebuild_ratio {
+            self.rebuild();
+            return true;
+        }
+        false
+    }
+
+    // 树的SAH代价，内部节点乘遍历代价，叶子乘求交代价1。
+    // 用叶子的面积之和而不是根节点的面积归一化：物体四散时根节点变大，按根归一化的代价反而下降，看不出树的退化；
+    // 叶子面积之和只随物体自身大小变化，结果即平均每次求交分摊的节点面积
+    pub fn cost(&self) -> f64 {
+        if self.root == NONE {
+            return 0.0;
+        }
+        let (mut interior, mut leaves) = (0.0, 0.0);
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index as usize];
+            if node.is_leaf() {
+                leaves += node.bbox.surface_area();
+            } else {
+                interior += node.bbox.surface_area();
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+        1.0 + TRAVERSAL_COST * interior / leaves.max(1e-12)
+    }
+
+    // 丢弃当前的树，对所有物体按SAH自顶向下重建
+    pub fn rebuild(&mut self) {
+        self.nodes.clear();
+        self.free_nodes.clear();
+        self.root = NONE;
+        self.bbox = Aabb::default();
+        let ids: Vec<u32> = (0..self.objects.len() as u32).filter(|&i| self.objects[i as usize].is_some()).collect();
+        if !ids.is_empty() {
+            let objects: Vec<Arc<dyn Hittable>> = ids.iter().map(|&i| self.objects[i as usize].clone().unwrap()).collect();
+            let mut prims = build_prims(&objects);
+            self.root = self.build(&mut prims, &ids, NONE);
+            self.bbox = self.nodes[self.root as usize].bbox.clone();
+        }
+        self.built_cost = self.cost();
+    }
+
+    fn add_slot(&mut self, object: Arc<dyn Hittable>) -> ObjectId {
+        assert_bounded(std::slice::from_ref(&object));
+        match self.free_objects.pop() {
+            Some(id) => {
+                self.objects[id as usize] = Some(object);
+                ObjectId(id)
+            }
+            None => {
+                self.objects.push(Some(object));
+                self.leaf_of.push(NONE);
+                ObjectId(self.objects.len() as u32 - 1)
+            }
+        }
+    }
+
+    fn alloc_node(&mut self, node: DynNode) -> u32 {
+        match self.free_nodes.pop() {
+            Some(index) => {
+                self.nodes[index as usize] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() as u32 - 1
+            }
+        }
+    }
+
+    fn new_leaf(&mut self, id: u32) -> u32 {
+        let bbox = self.objects[id as usize].as_ref().unwrap().bounding_box().clone();
+        let leaf = self.alloc_node(DynNode { bbox, parent: NONE, left: NONE, right: NONE, object: id });
+        self.leaf_of[id as usize] = leaf;
+        leaf
+    }
+
+    fn build(&mut self, prims: &mut [BuildPrim], ids: &[u32], parent: u32) -> u32 {
+        if prims.len() == 1 {
+            let leaf = self.new_leaf(ids[prims[0].index as usize]);
+            self.nodes[leaf as usize].parent = parent;
+            return leaf;
+        }
+        let mid = BvhNode::sah_partition(prims, 1).unwrap_or(prims.len() / 2);
+        let index = self.alloc_node(DynNode { bbox: Aabb::default(), parent, left: NONE, right: NONE, object: NONE });
+        let (left, right) = prims.split_at_mut(mid);
+        let left = self.build(left, ids, index);
+        let right = self.build(right, ids, index);
+        let bbox = Aabb::new_box(&self.nodes[left as usize].bbox, &self.nodes[right as usize].bbox);
+        let node = &mut self.nodes[index as usize];
+        (node.left, node.right, node.bbox) = (left, right, bbox);
+        index
+    }
+
+    // 从根向下走，每层比较把新叶子并入左、右子树或在此处作为兄弟的代价（Box2D的启发式），
+    // 下沉的代价包括沿途祖先包围盒面积的增加
+    fn insert_leaf(&mut self, leaf: u32) {
+        if self.root == NONE {
+            self.root = leaf;
+            self.nodes[leaf as usize].parent = NONE;
+            self.bbox = self.nodes[leaf as usize].bbox.clone();
+            return;
+        }
+        let bbox = self.nodes[leaf as usize].bbox.clone();
+        let mut sibling = self.root;
+        while !self.nodes[sibling as usize].is_leaf() {
+            let node = &self.nodes[sibling as usize];
+            let area = node.bbox.surface_area();
+            let combined = Aabb::new_box(&node.bbox, &bbox).surface_area();
+            let cost_here = 2.0 * combined;
+            let inherited = 2.0 * (combined - area);
+            let child_cost = |child: u32| {
+                let child = &self.nodes[child as usize];
+                let merged = Aabb::new_box(&child.bbox, &bbox).surface_area();
+                if child.is_leaf() {
+                    merged + inherited
+                } else {
+                    merged - child.bbox.surface_area() + inherited
+                }
+            };
+            let (cost_left, cost_right) = (child_cost(node.left), child_cost(node.right));
+            if cost_here < cost_left && cost_here < cost_right {
+                break;
+            }
+            sibling = if cost_left < cost_right { node.left } else { node.right };
+        }
+
+        let old_parent = self.nodes[sibling as usize].parent;
+        let bbox = Aabb::new_box(&self.nodes[sibling as usize].bbox, &bbox);
+        let parent = self.alloc_node(DynNode { bbox, parent: old_parent, left: sibling, right: leaf, object: NONE });
+        self.nodes[sibling as usize].parent = parent;
+        self.nodes[leaf as usize].parent = parent;
+        if old_parent == NONE {
+            self.root = parent;
+        } else {
+            self.replace_child(old_parent, sibling, parent);
+        }
+        self.refit_ancestors(old_parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: u32) {
+        if leaf == self.root {
+            self.root = NONE;
+            self.bbox = Aabb::default();
+            return;
+        }
+        let parent = self.nodes[leaf as usize].parent;
+        let grandparent = self.nodes[parent as usize].parent;
+        let node = &self.nodes[parent as usize];
+        let sibling = if node.left == leaf { node.right } else { node.left };
+        self.nodes[sibling as usize].parent = grandparent;
+        if grandparent == NONE {
+            self.root = sibling;
+        } else {
+            self.replace_child(grandparent, parent, sibling);
+        }
+        self.free_nodes.push(parent);
+        self.refit_ancestors(grandparent);
+    }
+
+    fn replace_child(&mut self, parent: u32, old: u32, new: u32) {
+        let node = &mut self.nodes[parent as usize];
+        if node.left == old {
+            node.left = new;
+        } else {
+            node.right = new;
+        }
+    }
+
+    // 从index向上重算到根
+    fn refit_ancestors(&mut self, mut index: u32) {
+        while index != NONE {
+            let node = &self.nodes[index as usize];
+            let bbox = Aabb::new_box(&self.nodes[node.left as usize].bbox, &self.nodes[node.right as usize].bbox);
+            let node = &mut self.nodes[index as usize];
+            node.bbox = bbox;
+            index = node.parent;
+        }
+        self.bbox = self.nodes[self.root as usize].bbox.clone();
+    }
+
+    fn refit_node(&mut self, index: u32) -> Aabb {
+        let node = &self.nodes[index as usize];
+        let bbox = if node.is_leaf() {
+            self.objects[node.object as usize].as_ref().unwrap().bounding_box().clone()
+        } else {
+            let (left, right) = (node.left, node.right);
+            Aabb::new_box(&self.refit_node(left), &self.refit_node(right))
+        };
+        self.nodes[index as usize].bbox = bbox.clone();
+        bbox
+    }
+
+    fn hit_node<'a>(&'a self, index: u32, r: &Ray, closest: &mut Interval, rec: &mut HitRecord<'a>) -> bool {
+        let node = &self.nodes[index as usize];
+        if !node.bbox.hit(r, &mut closest.clone()) {
+            return false;
+        }
+        if node.is_leaf() {
+            let object = self.objects[node.object as usize].as_ref().unwrap();
+            if object.hit_deferred(r, closest, rec) {
+                closest.max = rec.t;
+                return true;
+            }
+            return false;
+        }
+        let hit_left = self.hit_node(node.left, r, closest, rec);
+        let hit_right = self.hit_node(node.right, r, closest, rec);
+        hit_left || hit_right
+    }
+
+    fn occluded_node(&self, index: u32, r: &Ray, ray_t: &Interval) -> bool {
+        let node = &self.nodes[index as usize];
+        if !node.bbox.hit(r, &mut ray_t.clone()) {
+            return false;
+        }
+        if node.is_leaf() {
+            return self.objects[node.object as usize].as_ref().unwrap().occluded(r, ray_t);
+        }
+        self.occluded_node(node.left, r, ray_t) || self.occluded_node(node.right, r, ray_t)
+    }
+}
+
+impl Hittable for DynamicBvh {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        self.root != NONE && self.hit_node(self.root, r, &mut ray_t.clone(), rec)
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.root != NONE && self.occluded_node(self.root, r, ray_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::util::{random_double_range, INFINITY};
+    use crate::vec3::{Color, Point3, Vec3};
+
+    fn random_sphere(spread: f64) -> Arc<dyn Hittable> {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let center = Point3::new(random_double_range(-spread, spread), random_double_range(-spread, spread), random_double_range(-spread, spread));
+        Arc::new(Sphere::new(center, 0.3, mat))
+    }
+
+    #[test]
+    fn test_updates_match_brute_force() {
+        let mut list = HittableList::default();
+        for _ in 0..50 {
+            list.add(random_sphere(3.0));
+        }
+        let (mut bvh, ids) = DynamicBvh::new(&list);
+        let mut live: Vec<(ObjectId, Arc<dyn Hittable>)> = ids.into_iter().zip(list.objects).collect();
+
+        for round in 0..20 {
+            // 删掉几个、插入几个，其余的全部移动到新位置
+            for _ in 0..3 {
+                let (id, _) = live.swap_remove(round % live.len());
+                assert!(bvh.remove(id).is_some());
+                assert!(bvh.remove(id).is_none());
+            }
+            for _ in 0..3 {
+                let object = random_sphere(3.0);
+                live.push((bvh.insert(Arc::clone(&object)), object));
+            }
+            for (id, object) in live.iter_mut() {
+                *object = random_sphere(3.0 + round as f64);
+                bvh.update(*id, Arc::clone(object));
+            }
+            bvh.refit();
+
+            let brute = live.iter().fold(HittableList::default(), |mut l, (_, o)| {
+                l.add(Arc::clone(o));
+                l
+            });
+            for _ in 0..100 {
+                let r = Ray::new(Point3::zero(), Vec3::random_unit_vector());
+                let (mut a, mut b) = (HitRecord::default(), HitRecord::default());
+                let ray_t = Interval::new(0.001, INFINITY);
+                let hit = brute.hit(&r, &ray_t, &mut b);
+                assert_eq!(bvh.hit(&r, &ray_t, &mut a), hit);
+                assert_eq!(bvh.occluded(&r, &ray_t), hit);
+                assert_eq!(a.t, b.t);
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_refit_after_inserts_keeps_tree() {
+        let mut bvh = DynamicBvh::default();
+        let ids: Vec<ObjectId> = (0..100).map(|_| bvh.insert(random_sphere(5.0))).collect();
+        assert!(!bvh.refit());
+        assert!(!bvh.refit());
+
+        // 物体四散后才重建
+        for id in ids {
+            bvh.update(id, random_sphere(500.0));
+        }
+        assert!(bvh.refit());
+    }
+
+    #[test]
+    fn test_refit_triggers_rebuild() {
+        let mut list = HittableList::default();
+        for _ in 0..200 {
+            list.add(random_sphere(5.0));
+        }
+        let (mut bvh, ids) = DynamicBvh::new(&list);
+        assert!(!bvh.refit());
+
+        // 所有物体打乱位置后，原来的树结构不再贴合
+        for &id in &ids {
+            bvh.update(id, random_sphere(5.0));
+        }
+        assert!(bvh.refit());
+        assert!(!bvh.refit());
+    }
+}
@@ -0,0 +1,313 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::rtw_stb_image::RtwImage;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 某一层的最小/最大高度，第0层每个元素对应一个网格单元
+struct MinMaxLevel {
+    width: usize,
+    height: usize,
+    data: Vec<(f64, f64)>,
+}
+
+// 高度场地形：x、z方向为规则网格，y为高度
+pub struct Heightfield {
+    nx: usize,
+    nz: usize,
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    origin: Point3,
+    dx: f64,
+    dz: f64,
+    levels: Vec<MinMaxLevel>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Heightfield {
+    // heights为nx*nz个[0,1]内的采样，按行(z)存储；origin为最小角，size为三个方向的尺寸
+    pub fn new(heights: &[f64], nx: usize, nz: usize, origin: Point3, size: Vec3, mat: Arc<dyn Material>) -> Self {
+        assert!(nx >= 2 && nz >= 2 && heights.len() == nx * nz);
+        let heights: Vec<f64> = heights.iter().map(|h| origin.y() + h * size.y()).collect();
+        let dx = size.x() / (nx - 1) as f64;
+        let dz = size.z() / (nz - 1) as f64;
+
+        let normals = Self::vertex_normals(&heights, nx, nz, dx, dz);
+        let levels = Self::build_levels(&heights, nx, nz);
+
+        let (min_h, max_h) = levels.last().unwrap().data[0];
+        let bbox = Aabb::new_point(
+            &Point3::new(origin.x(), min_h, origin.z()),
+            &Point3::new(origin.x() + size.x(), max_h, origin.z() + size.z()),
+        )
+        .pad();
+
+        Self {
+            nx,
+            nz,
+            heights,
+            normals,
+            origin,
+            dx,
+            dz,
+            levels,
+            mat,
+            bbox,
+        }
+    }
+
+    // 灰度图作为高度图，图像的列对应x，行对应z
+    pub fn from_image(filename: &str, origin: Point3, size: Vec3, mat: Arc<dyn Material>) -> Self {
+        let image = RtwImage::new(filename);
+        let (nx, nz) = (image.width(), image.height());
+        let mut heights = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                let pixel = image.pixel_data(i, j);
+                heights.push((pixel[0] as f64 + pixel[1] as f64 + pixel[2] as f64) / (3.0 * 255.0));
+            }
+        }
+        Self::new(&heights, nx, nz, origin, size, mat)
+    }
+
+    fn vertex_normals(heights: &[f64], nx: usize, nz: usize, dx: f64, dz: f64) -> Vec<Vec3> {
+        let h = |i: usize, j: usize| heights[j * nx + i];
+        let mut normals = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                // 中心差分，边界处退化为单侧差分
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+                let dhdx = (h(i1, j) - h(i0, j)) / ((i1 - i0) as f64 * dx);
+                let dhdz = (h(i, j1) - h(i, j0)) / ((j1 - j0) as f64 * dz);
+                normals.push(Vec3::unit_vector(Vec3::new(-dhdx, 1.0, -dhdz)));
+            }
+        }
+        normals
+    }
+
+    // 最小/最大值金字塔，逐层合并2x2个子节点直到只剩一个
+    fn build_levels(heights: &[f64], nx: usize, nz: usize) -> Vec<MinMaxLevel> {
+        let (w, h) = (nx - 1, nz - 1);
+        let mut data = Vec::with_capacity(w * h);
+        for j in 0..h {
+            for i in 0..w {
+                let corners = [
+                    heights[j * nx + i],
+                    heights[j * nx + i + 1],
+                    heights[(j + 1) * nx + i],
+                    heights[(j + 1) * nx + i + 1],
+                ];
+                let min = corners.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = corners.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                data.push((min, max));
+            }
+        }
+        let mut levels = vec![MinMaxLevel { width: w, height: h, data }];
+
+        while levels.last().unwrap().width > 1 || levels.last().unwrap().height > 1 {
+            let prev = levels.last().unwrap();
+            let (w, h) = (prev.width.div_ceil(2), prev.height.div_ceil(2));
+            let mut data = vec![(f64::INFINITY, f64::NEG_INFINITY); w * h];
+            for j in 0..prev.height {
+                for i in 0..prev.width {
+                    let (lo, hi) = prev.data[j * prev.width + i];
+                    let node = &mut data[(j / 2) * w + i / 2];
+                    node.0 = node.0.min(lo);
+                    node.1 = node.1.max(hi);
+                }
+            }
+            levels.push(MinMaxLevel { width: w, height: h, data });
+        }
+        levels
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        Point3::new(
+            self.origin.x() + i as f64 * self.dx,
+            self.heights[j * self.nx + i],
+            self.origin.z() + j as f64 * self.dz,
+        )
+    }
+
+    // 节点(level, i, j)覆盖的包围盒
+    fn node_box(&self, level: usize, i: usize, j: usize) -> Aabb {
+        let span = 1 << level;
+        let cells_x = self.nx - 1;
+        let cells_z = self.nz - 1;
+        let (x0, x1) = (i * span, ((i + 1) * span).min(cells_x));
+        let (z0, z1) = (j * span, ((j + 1) * span).min(cells_z));
+        let lvl = &self.levels[level];
+        let (min_h, max_h) = lvl.data[j * lvl.width + i];
+        Aabb::new_point(
+            &Point3::new(self.origin.x() + x0 as f64 * self.dx, min_h, self.origin.z() + z0 as f64 * self.dz),
+            &Point3::new(self.origin.x() + x1 as f64 * self.dx, max_h, self.origin.z() + z1 as f64 * self.dz),
+        )
+        .pad()
+    }
+
+    // 自顶向下遍历金字塔，子节点按进入距离由近到远访问；
+    // rec为None时只判断遮挡，找到任意交点即返回
+    fn visit<'a>(&'a self, level: usize, i: usize, j: usize, r: &Ray, ray_t: &mut Interval, mut rec: Option<&mut HitRecord<'a>>) -> bool {
+        if level == 0 {
+            return self.hit_cell(i, j, r, ray_t, rec);
+        }
+
+        let child = &self.levels[level - 1];
+        let mut children = [(0.0, 0, 0); 4];
+        let mut count = 0;
+        for cj in (2 * j)..(2 * j + 2).min(child.height) {
+            for ci in (2 * i)..(2 * i + 2).min(child.width) {
+                let mut t = ray_t.clone();
+                if self.node_box(level - 1, ci, cj).hit(r, &mut t) {
+                    children[count] = (t.min, ci, cj);
+                    count += 1;
+                }
+            }
+        }
+        children[..count].sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut hit_anything = false;
+        for &(t_enter, ci, cj) in &children[..count] {
+            // 已找到的交点比后续节点更近，提前结束
+            if t_enter > ray_t.max {
+                break;
+            }
+            if self.visit(level - 1, ci, cj, r, ray_t, rec.as_deref_mut()) {
+                if rec.is_none() {
+                    return true;
+                }
+                hit_anything = true;
+            }
+        }
+        hit_anything
+    }
+
+    // 格子(i, j)中第k个三角形的三个顶点
+    fn cell_triangle(i: usize, j: usize, k: usize) -> [(usize, usize); 3] {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        [[0, 1, 2], [0, 2, 3]][k].map(|c| corners[c])
+    }
+
+    // 命中的三角形记为 (j * nx + i) * 2 + k 存入HitRecord::part，重心坐标暂存在u、v中
+    fn hit_cell<'a>(&'a self, i: usize, j: usize, r: &Ray, ray_t: &mut Interval, mut rec: Option<&mut HitRecord<'a>>) -> bool {
+        let mut hit_anything = false;
+        for k in 0..2 {
+            let [a, b, c] = Self::cell_triangle(i, j, k);
+            let (p0, p1, p2) = (self.vertex(a.0, a.1), self.vertex(b.0, b.1), self.vertex(c.0, c.1));
+
+            // Moller-Trumbore
+            let e1 = p1 - p0;
+            let e2 = p2 - p0;
+            let pvec = Vec3::cross(r.dir, e2);
+            let det = Vec3::dot(e1, pvec);
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            let inv_det = 1.0 / det;
+            let tvec = r.orig - p0;
+            let b1 = Vec3::dot(tvec, pvec) * inv_det;
+            if !(0.0..=1.0).contains(&b1) {
+                continue;
+            }
+            let qvec = Vec3::cross(tvec, e1);
+            let b2 = Vec3::dot(r.dir, qvec) * inv_det;
+            if b2 < 0.0 || b1 + b2 > 1.0 {
+                continue;
+            }
+            let t = Vec3::dot(e2, qvec) * inv_det;
+            if !ray_t.surrounds(t) {
+                continue;
+            }
+            let Some(rec) = rec.as_deref_mut() else {
+                return true;
+            };
+
+            ray_t.max = t;
+            rec.defer(self, t);
+            rec.part = (j * self.nx + i) * 2 + k;
+            (rec.u, rec.v) = (b1, b2);
+            hit_anything = true;
+        }
+        hit_anything
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let mut t = ray_t.clone();
+        if !self.bbox.hit(r, &mut t) {
+            return false;
+        }
+        let mut t = ray_t.clone();
+        self.visit(self.levels.len() - 1, 0, 0, r, &mut t, Some(rec))
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        let cell = rec.part / 2;
+        let [a, b, c] = Self::cell_triangle(cell % self.nx, cell / self.nx, rec.part % 2);
+        let (b1, b2) = (rec.u, rec.v);
+
+        // 顶点法线插值得到平滑着色
+        let n = |v: (usize, usize)| self.normals[v.1 * self.nx + v.0];
+        let normal = Vec3::unit_vector((1.0 - b1 - b2) * n(a) + b1 * n(b) + b2 * n(c));
+
+        rec.p = r.at(rec.t);
+        rec.set_face_normal(r, normal);
+        // v翻转，使图像第一行对应z最小处，与ImageTexture一致
+        rec.u = (rec.p.x() - self.origin.x()) / (self.dx * (self.nx - 1) as f64);
+        rec.v = 1.0 - (rec.p.z() - self.origin.z()) / (self.dz * (self.nz - 1) as f64);
+        rec.set_material(&*self.mat);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        if !self.bbox.hit(r, &mut ray_t.clone()) {
+            return false;
+        }
+        self.visit(self.levels.len() - 1, 0, 0, r, &mut ray_t.clone(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::util;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_hit_ramp() {
+        // 沿x方向线性升高的斜坡，网格尺寸非2的幂
+        let (nx, nz) = (7, 5);
+        let heights: Vec<f64> = (0..nx * nz).map(|k| (k % nx) as f64 / (nx - 1) as f64).collect();
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let field = Heightfield::new(&heights, nx, nz, Point3::zero(), Vec3::new(6.0, 3.0, 4.0), mat);
+
+        let mut rec = HitRecord::default();
+        for (x, z) in [(0.3, 0.2), (2.5, 3.9), (5.7, 1.1)] {
+            let r = Ray::new(Point3::new(x, 10.0, z), Vec3::new(0.0, -1.0, 0.0));
+            assert!(field.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+            assert!((rec.p.y() - 0.5 * x).abs() < 1e-8);
+            assert!((rec.normal - Vec3::unit_vector(Vec3::new(-0.5, 1.0, 0.0))).length() < 1e-8);
+        }
+
+        let r = Ray::new(Point3::new(7.0, 10.0, 1.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(!field.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+    }
+}
@@ -1,22 +1,88 @@
 use crate::ray::Ray;
 use crate::vec3::Point3;
 use crate::vec3::Vec3;
+use crate::vec3::Color;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::util;
 use std::sync::Arc;
 use crate::aabb::Aabb;
-#[derive(Default,Clone)]
-pub struct HitRecord {
+use crate::mat4::Mat4;
+use crate::packet::RayPacket;
+// 材质借用自被击中的物体，记录可以直接按位复制，求交时不再增减Arc引用计数。
+// 遍历时图元只确定t并记下自己（object），p、法线、UV和材质等表面信息由finish在最终交点上一次算出；
+// 外层的变换只把世界到对象空间的矩阵累乘进to_object，不再逐个候选交点变换p和法线
+#[derive(Default,Clone,Copy)]
+pub struct HitRecord<'a> {
     pub p : Point3,
     pub normal : Vec3,
     pub t : f64,//交点处光线的t值
     pub front_face : bool,
-    pub mat: Option<Arc<dyn Material>>,
-    pub u: f64,
+    pub mat: Option<&'a dyn Material>,
+    pub u: f64,//推迟计算时图元可暂存重心坐标等，surface中改写为纹理坐标
     pub v: f64,
+    pub tangent: Vec3,//曲线等细长几何的切线方向，其他物体为零向量
+    pub color: Option<Color>,//点云等逐点着色的颜色，由VertexColor材质使用
+    pub object: Option<&'a dyn Hittable>,//尚未补全表面信息的图元
+    pub part: usize,//图元内部的部件编号（点云中的点、圆柱的侧面或端面等），由图元自己解释
+    pub to_object: Option<Mat4>,//世界空间到图元所在空间的变换，None为恒等
+    pub mat_override: Option<&'a dyn Material>,//实例覆盖的材质
 }
-impl HitRecord {
+impl<'a> HitRecord<'a> {
+    // 图元确认交点时设置材质，同时清掉只有个别图元才填写的字段：
+    // 容器在多个物体之间复用同一条记录，不清掉的话较近的交点会沿用较远交点的曲线切线、点云颜色或外层变换
+    pub fn set_material(&mut self, mat: &'a dyn Material) {
+        self.mat = Some(mat);
+        self.tangent = Vec3::zero();
+        self.color = None;
+        self.object = None;
+        self.to_object = None;
+        self.mat_override = None;
+    }
+
+    // 图元接受一个候选交点，表面信息推迟到finish
+    pub fn defer(&mut self, object: &'a dyn Hittable, t: f64) {
+        self.t = t;
+        self.object = Some(object);
+        self.to_object = None;
+        self.mat_override = None;
+    }
+
+    // 外层变换在内部物体命中后调用，m为该层世界到对象空间的变换
+    pub fn push_transform(&mut self, m: &Mat4) {
+        self.to_object = Some(match self.to_object {
+            Some(inner) => inner * *m,
+            None => *m,
+        });
+    }
+
+    // 在最终交点上补全表面信息：在图元空间中计算，再变换回世界空间。r为世界空间的光线
+    pub fn finish(&mut self, r: &Ray) {
+        let (object, to_object, mat_override) = (self.object, self.to_object, self.mat_override);
+        let local_r = match &to_object {
+            Some(m) => Ray::new_time(m.transform_point(r.orig), m.transform_vector(r.dir), r.tm),
+            None => *r,
+        };
+        if let Some(object) = object {
+            object.surface(&local_r, self);
+        }
+        if let Some(inv) = to_object {
+            let m = inv.inverse().expect("composed transforms are invertible");
+            self.p = m.transform_point(self.p);
+            // 法线用逆矩阵的转置变换
+            self.normal = Vec3::unit_vector(inv.linear().transpose() * self.normal);
+            if !self.tangent.near_zero() {
+                self.tangent = Vec3::unit_vector(m.transform_vector(self.tangent));
+            }
+        }
+        if let Some(mat) = mat_override {
+            self.mat = Some(mat);
+        }
+        self.object = None;
+        self.to_object = None;
+        self.mat_override = None;
+    }
+
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
         self.front_face = Vec3::dot(r.dir, outward_normal) < 0.0;
         self.normal = if self.front_face {
@@ -28,12 +94,35 @@ impl HitRecord {
 }
 pub trait Hittable : Send + Sync {
     // fn hit(&self ,r:&Ray,ray_tmin:f64,ray_tmax:f64,rec:&mut HitRecord)->bool;
-    fn hit(&self, r: &Ray, ray_t: &Interval, hit_record: &mut HitRecord) -> bool;
+    // 求最近交点并填写完整的记录
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, hit_record: &mut HitRecord<'a>) -> bool;
+    // 求最近交点，记录可以只含t和待补全的图元，由调用者在最终交点上调用finish。
+    // 默认退回到hit；容器、变换和支持推迟计算的图元覆盖此方法，其hit即hit_deferred加finish
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        self.hit(r, ray_t, hit_record)
+    }
+    // 推迟计算的图元在此由rec.t（及rec.part、u、v中暂存的数据）补全表面信息，r为图元空间中的光线
+    fn surface<'a>(&'a self, _r: &Ray, _rec: &mut HitRecord<'a>) {}
     fn bounding_box(&self) -> &Aabb;
+    // 区间内是否有任意交点，用于阴影光线和环境光遮蔽：找到一个就返回，不求最近交点也不填写HitRecord。
+    // 默认退回到hit_deferred；容器和图元覆盖此方法
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.hit_deferred(r, ray_t, &mut HitRecord::default())
+    }
+    // 光线包求交，默认逐条调用hit；BVH和常用图元覆盖此方法逐通道计算
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        for i in 0..packet.len() {
+            let ray_t = packet.interval(i);
+            if packet.active[i] && self.hit(&packet.rays[i], &ray_t, &mut packet.recs[i]) {
+                packet.record_hit(i);
+            }
+        }
+    }
 }
 pub struct Translate {
     object: Arc<dyn Hittable>,
     offset: Vec3,
+    to_object: Mat4,
     bbox: Aabb,
 }
 
@@ -41,32 +130,46 @@ impl Translate {
     pub fn new(object: Arc<dyn Hittable>, offset: Vec3) -> Self {
         let bbox = object.bounding_box() + offset;
         Self {
-            object,offset,bbox
+            object,offset,to_object: Mat4::translate(-offset),bbox
         }
     }
 }
 
 impl Hittable for Translate {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
         // 将光线向后移动偏移量
         let offset_r = Ray::new_time(r.orig - self.offset, r.dir, r.tm);
         // 确定在偏移光线上是否存在交点
-        if !self.object.hit(&offset_r, ray_t, rec) {
+        if !self.object.hit_deferred(&offset_r, ray_t, rec) {
             return false;
         }
-        rec.p += self.offset;
+        // 交点在finish中移回世界空间
+        rec.push_transform(&self.to_object);
 
         true
     }
     fn bounding_box(&self) -> &Aabb {
         &self.bbox
     }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.object.occluded(&Ray::new_time(r.orig - self.offset, r.dir, r.tm), ray_t)
+    }
 }
 
 pub struct RotateY {
     object: Arc<dyn Hittable>,
     sin_theta: f64,
     cos_theta: f64,
+    to_object: Mat4,
     bbox: Aabb,
 }
 
@@ -75,44 +178,18 @@ impl RotateY {
                let radians = angle.to_radians();
                let sin_theta = radians.sin();
                let cos_theta = radians.cos();
-               let bbox = p.bounding_box();
-        
-               let mut min = Point3::new(util::INFINITY, util::INFINITY, util::INFINITY);
-               let mut max = Point3::new(-util::INFINITY, -util::INFINITY, -util::INFINITY);
-        
-               (0..2).for_each(|i| {
-                   (0..2).for_each(|j| {
-                       (0..2).for_each(|k| {
-                           let x = i as f64 * bbox.x.max + (1 - i) as f64 * bbox.x.min;
-                           let y = j as f64 * bbox.y.max + (1 - j) as f64 * bbox.y.min;
-                           let z = k as f64 * bbox.z.max + (1 - k) as f64 * bbox.z.min;
-        
-                           let newx = cos_theta * x + sin_theta * z;
-                           let newz = -sin_theta * x + cos_theta * z;
-        
-                           let tester = Vec3::new(newx, y, newz);
-        
-                           (0..3).for_each(|c| {
-                               min[c] = min[c].min(tester[c]);
-                               max[c] = max[c].max(tester[c]);
-                           })
-                       })
-                   })
-               });
-        
-               let bbox = Aabb::new_point(&min, &max);
+               let bbox = Transform::transform_box(p.bounding_box(), &Mat4::rotate_y(angle));
                Self {
                    object: p,
                    sin_theta,
                    cos_theta,
+                   to_object: Mat4::rotate_y(-angle),
                    bbox,
                }
            }
-}
 
-impl Hittable for RotateY {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
-        //将光线从世界空间变换到对象空间
+    //将光线从世界空间变换到对象空间
+    fn to_object(&self, r: &Ray) -> Ray {
         let mut origin = r.orig;
         let mut direction = r.dir;
 
@@ -122,21 +199,107 @@ impl Hittable for RotateY {
         direction[0] = self.cos_theta * r.dir[0] - self.sin_theta * r.dir[2];
         direction[2] = self.sin_theta * r.dir[0] + self.cos_theta * r.dir[2];
 
-        let rotated_r = Ray::new_time(origin, direction, r.tm);
-        if !self.object.hit(&rotated_r, ray_t, rec) {
+        Ray::new_time(origin, direction, r.tm)
+    }
+}
+
+impl Hittable for RotateY {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let rotated_r = self.to_object(r);
+        if !self.object.hit_deferred(&rotated_r, ray_t, rec) {
+            return false;
+        }
+        //交点、法线和切线在finish中从对象空间变换到世界空间
+        rec.push_transform(&self.to_object);
+
+        true
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.object.occluded(&self.to_object(r), ray_t)
+    }
+}
+
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    inv: Mat4,
+    bbox: Aabb,
+}
+
+impl Transform {
+    pub fn new(object: Arc<dyn Hittable>, m: Mat4) -> Self {
+        let inv = m.inverse().expect("Transform matrix must be invertible");
+        let bbox = Self::transform_box(object.bounding_box(), &m);
+        Self {
+            object,
+            inv,
+            bbox,
+        }
+    }
+
+    pub(crate) fn transform_box(bbox: &Aabb, m: &Mat4) -> Aabb {
+        // 无穷大的角点乘上矩阵中的0会得到NaN，无界的盒子变换后仍视为无界
+        if !bbox.is_bounded() {
+            return Aabb::UNIVERSE;
+        }
+        let mut min = Point3::new(util::INFINITY, util::INFINITY, util::INFINITY);
+        let mut max = Point3::new(-util::INFINITY, -util::INFINITY, -util::INFINITY);
+
+        for i in 0..2 {
+            for j in 0..2 {
+                for k in 0..2 {
+                    let x = if i == 1 { bbox.x.max } else { bbox.x.min };
+                    let y = if j == 1 { bbox.y.max } else { bbox.y.min };
+                    let z = if k == 1 { bbox.z.max } else { bbox.z.min };
+                    let tester = m.transform_point(Point3::new(x, y, z));
+                    for c in 0..3 {
+                        min[c] = min[c].min(tester[c]);
+                        max[c] = max[c].max(tester[c]);
+                    }
+                }
+            }
+        }
+
+        Aabb::new_point(&min, &max)
+    }
+
+    // 方向不归一化，对象空间中的t与世界空间一致
+    fn to_object(&self, r: &Ray) -> Ray {
+        Ray::new_time(
+            self.inv.transform_point(r.orig),
+            self.inv.transform_vector(r.dir),
+            r.tm,
+        )
+    }
+}
+
+impl Hittable for Transform {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    // 交点、法线（逆矩阵的转置）和切线在finish中变换到世界空间
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        if !self.object.hit_deferred(&self.to_object(r), ray_t, rec) {
             return false;
-        }  
-        //将交点从对象空间变换到世界空间
-        let mut p = rec.p;
-        p[0] = self.cos_theta * rec.p[0] + self.sin_theta * rec.p[2];
-        p[2] = -self.sin_theta * rec.p[0] + self.cos_theta * rec.p[2];  
-        //将法线从对象空间变换到世界空间
-        let mut normal = rec.normal;
-        normal[0] = self.cos_theta * rec.normal[0] + self.sin_theta * rec.normal[2];
-        normal[2] = -self.sin_theta * rec.normal[0] + self.cos_theta * rec.normal[2];
-
-        rec.p = p;
-        rec.normal = normal;
+        }
+        rec.push_transform(&self.inv);
 
         true
     }
@@ -144,4 +307,91 @@ impl Hittable for RotateY {
     fn bounding_box(&self) -> &Aabb {
         &self.bbox
     }
-}
\ No newline at end of file
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.object.occluded(&self.to_object(r), ray_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use crate::instance::Prototype;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn test_surface_deferred_to_final_hit() {
+        let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::zero(), 1.0, white.clone()));
+
+        // 旋转后平移到z=-5的单位球，和放大两倍、移到z=-10并覆盖材质的实例
+        let mut list = HittableList::default();
+        list.add(Arc::new(Translate::new(Arc::new(RotateY::new(sphere.clone(), 90.0)), Vec3::new(0.0, 0.0, -5.0))));
+        let big = Prototype::from_hittable(Arc::new(Transform::new(sphere, Mat4::scale(Vec3::new(2.0, 2.0, 2.0)))));
+        list.add(Arc::new(big.instance_with_material(Mat4::translate(Vec3::new(0.0, 0.0, -10.0)), red.clone())));
+
+        // 遍历只确定t和图元，finish后才有交点、法线和材质
+        let t = Interval::new(0.001, util::INFINITY);
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        assert!(list.hit_deferred(&r, &t, &mut rec));
+        assert!((rec.t - 9.0).abs() < 1e-9);
+        assert!(rec.object.is_some() && rec.to_object.is_some() && rec.mat.is_none());
+        rec.finish(&r);
+        assert!((rec.p - Point3::new(0.0, 0.0, -4.0)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((rec.v - 0.5).abs() < 1e-9);
+        assert!(std::ptr::addr_eq(rec.mat.unwrap(), &*white));
+        assert!(rec.object.is_none() && rec.to_object.is_none());
+
+        // 从单位球上方经过，只击中放大的实例
+        let r = Ray::new(Point3::new(0.0, 1.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut rec = HitRecord::default();
+        assert!(list.hit(&r, &t, &mut rec));
+        let z = 1.75f64.sqrt();
+        assert!((rec.t - (15.0 - z)).abs() < 1e-9);
+        assert!((rec.p - Point3::new(0.0, 1.5, z - 10.0)).length() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.75, z / 2.0)).length() < 1e-9);
+        assert!(std::ptr::addr_eq(rec.mat.unwrap(), &*red));
+
+        // 较近的交点来自另一个物体时，外层的变换和材质覆盖不会沿用
+        let near = Arc::new(Sphere::new(Point3::new(0.0, 1.5, 0.0), 0.5, white.clone()));
+        list.add(near);
+        let mut rec = HitRecord::default();
+        assert!(list.hit(&r, &t, &mut rec));
+        assert!((rec.t - 4.5).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!(std::ptr::addr_eq(rec.mat.unwrap(), &*white));
+    }
+
+    #[test]
+    fn test_transform_scaled_rotated_sphere() {
+        // 单位球沿x拉长两倍，绕y转90度后长轴沿z，再移到z=-5
+        let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::zero(), 1.0, white));
+        let m = Mat4::translate(Vec3::new(0.0, 0.0, -5.0)) * Mat4::rotate_y(90.0) * Mat4::scale(Vec3::new(2.0, 1.0, 1.0));
+        let ellipsoid = Transform::new(sphere, m);
+
+        let bbox = ellipsoid.bounding_box();
+        for (a, (min, max)) in [(-1.0, 1.0), (-1.0, 1.0), (-7.0, -3.0)].into_iter().enumerate() {
+            assert!((bbox.axis(a).min - min).abs() < 1e-9 && (bbox.axis(a).max - max).abs() < 1e-9);
+        }
+
+        let t = Interval::new(0.001, util::INFINITY);
+        let mut rec = HitRecord::default();
+        assert!(ellipsoid.hit(&Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+        assert!((rec.t - 8.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+
+        // 偏离长轴的交点，法线是椭球的梯度方向(x, y, (z+5)/4)，直接变换对象空间的法线会得到错误的方向
+        assert!(ellipsoid.hit(&Ray::new(Point3::new(0.6, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+        assert!((rec.t - 8.4).abs() < 1e-9);
+        assert!((rec.p - Point3::new(0.6, 0.0, -3.4)).length() < 1e-9);
+        assert!((rec.normal - Vec3::unit_vector(Vec3::new(0.6, 0.0, 0.4))).length() < 1e-9);
+        assert!(!ellipsoid.hit(&Ray::new(Point3::new(1.1, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+    }
+
+}
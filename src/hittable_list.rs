@@ -1,5 +1,6 @@
 use crate::hittable::{HitRecord,Hittable};
 use crate::ray::Ray;
+use crate::packet::RayPacket;
 use crate::interval::Interval;
 use crate::vec3::Vec3;
 use crate::aabb::*;
@@ -12,9 +13,10 @@ pub struct HittableList {
 
 impl HittableList {
     pub fn new(object: Arc<dyn Hittable>) -> Self {
+        let bbox = object.bounding_box().clone();
         Self {
             objects:vec![object],
-            bbox: Aabb::default(),
+            bbox,
         }
     }
     pub fn add(&mut self, object: Arc<dyn Hittable>) {
@@ -24,16 +26,23 @@ impl HittableList {
     
 }
 impl Hittable for HittableList {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
-        let mut temp_rec = HitRecord::default();
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        // hit_deferred只在命中时写入记录（BvhNode也依赖这一点），较近的交点直接覆盖rec，不必经过临时记录复制
         let mut hit_anything = false;
         let mut closest_so_far = ray_t.max;
 
         for object in self.objects.iter() {
-            if object.hit(r, &Interval::new(ray_t.min, closest_so_far), &mut temp_rec) {
+            if object.hit_deferred(r, &Interval::new(ray_t.min, closest_so_far), rec) {
                 hit_anything = true;
-                closest_so_far = temp_rec.t;
-                *rec = temp_rec.clone();
+                closest_so_far = rec.t;
             }
         }
 
@@ -44,4 +53,14 @@ impl Hittable for HittableList {
         &self.bbox
     }
 
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.objects.iter().any(|object| object.occluded(r, ray_t))
+    }
+
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        for object in self.objects.iter() {
+            object.hit_packet(packet);
+        }
+    }
+
 }
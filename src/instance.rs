@@ -0,0 +1,141 @@
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable, Transform};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::mat4::Mat4;
+use crate::ray::Ray;
+use crate::aabb::Aabb;
+use std::sync::Arc;
+
+// 原型：只建一次BVH，被多个实例共享
+#[derive(Clone)]
+pub struct Prototype {
+    geometry: Arc<dyn Hittable>,
+}
+
+impl Prototype {
+    // 与场景一样，无界物体留在原型的BVH之外
+    pub fn new(list: &HittableList) -> Self {
+        Self {
+            geometry: BvhNode::new_boxed(list),
+        }
+    }
+
+    pub fn from_hittable(geometry: Arc<dyn Hittable>) -> Self {
+        Self { geometry }
+    }
+
+    pub fn instance(&self, m: Mat4) -> Instance {
+        Instance::new(self, m, None)
+    }
+
+    pub fn instance_with_material(&self, m: Mat4, mat: Arc<dyn Material>) -> Instance {
+        Instance::new(self, m, Some(mat))
+    }
+}
+
+pub struct Instance {
+    transform: Transform,
+    mat: Option<Arc<dyn Material>>,
+}
+
+impl Instance {
+    pub fn new(prototype: &Prototype, m: Mat4, mat: Option<Arc<dyn Material>>) -> Self {
+        Self {
+            transform: Transform::new(Arc::clone(&prototype.geometry), m),
+            mat,
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        if !self.transform.hit_deferred(r, ray_t, rec) {
+            return false;
+        }
+        // 覆盖原型的材质，finish在图元设置材质之后应用
+        if let Some(mat) = &self.mat {
+            rec.mat_override = Some(&**mat);
+        }
+
+        true
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        self.transform.bounding_box()
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.transform.occluded(r, ray_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    #[test]
+    fn test_instances_share_prototype() {
+        let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+        let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let ball = Prototype::new(&HittableList::new(Arc::new(Sphere::new(Point3::zero(), 1.0, white.clone()))));
+
+        // 两个实例引用同一个BVH，只是变换不同
+        let left = ball.instance(Mat4::translate(Vec3::new(-3.0, 0.0, 0.0)));
+        let right = ball.instance_with_material(Mat4::translate(Vec3::new(3.0, 0.0, 0.0)) * Mat4::scale(Vec3::new(2.0, 2.0, 2.0)), red.clone());
+        assert_eq!(Arc::strong_count(&ball.geometry), 3);
+
+        let t = Interval::new(0.001, f64::INFINITY);
+        let mut rec = HitRecord::default();
+        assert!(left.hit(&Ray::new(Point3::new(-3.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+        assert!((rec.t - 4.0).abs() < 1e-8);
+        assert!((rec.p - Point3::new(-3.0, 0.0, 1.0)).length() < 1e-8);
+        assert!(std::ptr::addr_eq(rec.mat.unwrap(), &*white));
+        assert!(!left.hit(&Ray::new(Point3::new(3.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+
+        // 放大两倍的实例，材质被覆盖
+        assert!(right.hit(&Ray::new(Point3::new(3.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-8);
+        assert!(std::ptr::addr_eq(rec.mat.unwrap(), &*red));
+        assert!(right.bounding_box().axis(0).min < 1.0 + 1e-3 && right.bounding_box().axis(0).max > 5.0 - 1e-3);
+    }
+
+    #[test]
+    fn test_prototype_with_plane() {
+        use crate::plane::Plane;
+
+        let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::new(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, white.clone())));
+        list.add(Arc::new(Plane::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), white)));
+        let ground = Prototype::new(&list);
+
+        // 旋转后的包围盒仍是无界的，不会因为无穷大乘0变成NaN
+        let tilted = ground.instance(Mat4::translate(Vec3::new(0.0, -2.0, 0.0)) * Mat4::rotate_y(30.0));
+        let bbox = tilted.bounding_box();
+        assert!(!bbox.is_bounded());
+        assert!((0..3).all(|a| !bbox.axis(a).min.is_nan() && !bbox.axis(a).max.is_nan()));
+
+        // 远离球的光线击中平面，球正上方的光线先击中球
+        let t = Interval::new(0.001, f64::INFINITY);
+        let mut rec = HitRecord::default();
+        assert!(tilted.hit(&Ray::new(Point3::new(50.0, 3.0, 7.0), Vec3::new(0.0, -1.0, 0.0)), &t, &mut rec));
+        assert!((rec.t - 5.0).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-8);
+        assert!(tilted.hit(&Ray::new(Point3::new(0.3, 3.0, 0.4), Vec3::new(0.0, -1.0, 0.0)), &t, &mut rec));
+        assert!((rec.t - (4.0 - 0.75f64.sqrt())).abs() < 1e-8);
+    }
+
+}
@@ -15,19 +15,63 @@ pub mod camera;
 mod perlin;
 mod qard;
 mod constant_medium;
+mod mat4;
+mod instance;
+mod onb;
+mod cylinder;
+mod cone;
+mod torus;
+mod capsule;
+mod plane;
+mod csg;
+mod sdf;
+mod heightfield;
+mod mesh;
+mod bezier;
+mod subdivision;
+mod curve;
+mod point_cloud;
+mod motion;
+mod animation;
+mod aperture;
+mod wide_bvh;
+mod packet;
+mod dynamic_bvh;
 use std::process::Command;
 use std::time::Instant;
 use constant_medium::ConstantMedium;
-use qard::Quad;
+use qard::{Quad,Triangle,Ellipse,Annulus,make_tetrahedron,make_pyramid,box_sides};
 use crate::qard::make_box;
 use texture::{SolidColor,Texture,CheckerTexture,ImageTexture,NoiseTexture};
-use bvh::BvhNode;
-use camera::Camera;
+use bvh::{BvhNode, BvhSplit, FlatBvh};
+use wide_bvh::WideBvh;
+use dynamic_bvh::{DynamicBvh, ObjectId};
+use camera::{Camera, Integrator, PhysicalExposure, Projection, StereoLayout};
+use aperture::{Aperture, ApertureMask};
 use util::{random_double, random_double_range};
-use crate::material::{Lambertian,Metal,Material,DiffuseLight,Dielectric};
+use crate::material::{Lambertian,Metal,Material,DiffuseLight,Dielectric,Hair,VertexColor};
 use interval::Interval;
 use hittable_list::HittableList;
-use hittable::{HitRecord, Hittable, RotateY,Translate};
+use hittable::{HitRecord, Hittable, RotateY,Translate,Transform};
+use mat4::{Mat4, Quat};
+use motion::{Keyframe, MotionTransform};
+use animation::{CameraKey, CameraTrack, Sequence};
+use instance::Prototype;
+use cylinder::Cylinder;
+use cone::Cone;
+use torus::Torus;
+use capsule::Capsule;
+use plane::Plane;
+use csg::{Csg, Solid};
+use sdf::*;
+use heightfield::Heightfield;
+use perlin::Perlin;
+use mesh::TriangleMesh;
+use bezier::{BezierPatch, load_patches, tessellate_patches};
+use subdivision::{PolyMesh, loop_subdivide, catmull_clark_subdivide};
+use curve::{Curve, CurveType};
+use point_cloud::{PointCloud, PointShape};
+use aabb::Aabb;
 use sphere::Sphere;
 use ray::Ray;
 use vec3::{Color,Point3,Vec3};
@@ -56,9 +100,9 @@ fn random_spheres(){
     let ground_material: Arc<dyn Material> = Arc::new(
         Lambertian::new_texture(Arc::clone(&checker))
         );
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
         ground_material
     )));
 
@@ -95,8 +139,7 @@ fn random_spheres(){
     let material3 = Arc::new(material::Metal::new(Color::new(0.7,0.7,0.99),0.0));
     world.add(Arc::new(Sphere::new(Point3::new(4.0,1.0,0.0),1.0,material3)));
     
-    let bvh_node = Arc::new(BvhNode::new(&mut world));
-    world = HittableList::new(bvh_node);
+    world = HittableList::new(BvhNode::new_boxed(&world));
 
     let mut cam = Camera::default();
     cam.aspect_ratio = 16.0 / 9.0;
@@ -187,9 +230,9 @@ fn two_perlin_spheres() {
 
     let pertext: Arc::<dyn Texture + Send + Sync> = Arc::new(NoiseTexture::new(4.0));
     world.add(Arc::new(
-        Sphere::new(
-            Point3::new(0.0, -1000.0, 0.0),
-            1000.0,
+        Plane::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
             Arc::new(Lambertian::new_texture(Arc::clone(&pertext)))
         )
     ));
@@ -542,8 +585,20 @@ fn cornell_smoke() {
 }
 
 fn final_scene(image_width: u32, samples_per_pixel: usize, max_depth: i32) {
+    let (world, cam) = final_scene_setup(image_width, samples_per_pixel, max_depth, &BvhNode::new_boxed);
+    render(cam,&world);
+    // cam.render(&world);
+}
+type BvhBuilder = dyn Fn(&HittableList) -> Arc<dyn Hittable + Send + Sync>;
+// 内部的两组小盒子、小球用build建BVH
+fn final_scene_setup(image_width: u32, samples_per_pixel: usize, max_depth: i32, build: &BvhBuilder) -> (HittableList, Camera) {
     let mut boxes1 = HittableList::default();
     let ground: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.48, 0.83, 0.53)));
+    let unit_box = Prototype::from_hittable(make_box(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 1.0, 1.0),
+        Arc::clone(&ground)
+    ));
 
     let boxes_per_side = 20;
     (0..boxes_per_side).for_each(|i| {
@@ -556,19 +611,15 @@ fn final_scene(image_width: u32, samples_per_pixel: usize, max_depth: i32) {
         let y1 = util::random_double_range(1.0, 101.0);
         let z1 = z0 + w;
 
-        boxes1.add(
-            make_box(
-                Point3::new(x0, y0, z0),
-                Point3::new(x1, y1, z1),
-                Arc::clone(&ground)
-            )
-        );
+        boxes1.add(Arc::new(unit_box.instance(
+            Mat4::translate(Point3::new(x0, y0, z0)) * Mat4::scale(Vec3::new(x1 - x0, y1 - y0, z1 - z0))
+        )));
         });
     });
 
     let mut world = HittableList::default();
 
-    world.add(Arc::new(BvhNode::new(&mut boxes1)));
+    world.add(build(&boxes1));
 
     let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(7.0, 7.0, 7.0)));
     world.add(Arc::new(
@@ -635,21 +686,21 @@ fn final_scene(image_width: u32, samples_per_pixel: usize, max_depth: i32) {
 
     let mut boxes2 = HittableList::default();
     let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    // 同一个球的原型被1000个实例共享
+    let ball = Prototype::new(&HittableList::new(Arc::new(
+        Sphere::new(Point3::new(0.0, 0.0, 0.0), 10.0, white)
+    )));
     let ns = 1000;
     (0..ns).for_each(|_| {
         boxes2.add(
-            Arc::new(Sphere::new(
-                Point3::random_range(0.0, 165.0),
-                10.0,
-                Arc::clone(&white)
-            ))
+            Arc::new(ball.instance(Mat4::translate(Point3::random_range(0.0, 165.0))))
         );
     });
 
     world.add(Arc::new(
         Translate::new(
             Arc::new(RotateY::new(
-                Arc::new(BvhNode::new(&mut boxes2)),
+                build(&boxes2),
                 15.0
             )),
             vec3::Vec3::new(-100.0, 270.0, 395.0)
@@ -671,10 +722,1221 @@ fn final_scene(image_width: u32, samples_per_pixel: usize, max_depth: i32) {
 
     cam.defocus_angle = 0.0;
 
+    (world, cam)
+}
+// 在final_scene上比较中位数划分、SAH两种构建方式的树形BVH与线性化BVH、4路和8路BVH的建树和渲染耗时
+fn bvh_benchmark() {
+    let median = |list: &HittableList| BvhNode::new_boxed_with(list, BvhSplit::Median);
+    let sah = |list: &HittableList| BvhNode::new_boxed_with(list, BvhSplit::default());
+    let flat = |list: &HittableList| Arc::new(FlatBvh::new(list)) as Arc<dyn Hittable + Send + Sync>;
+    let wide4 = |list: &HittableList| Arc::new(WideBvh::<4>::new(list)) as Arc<dyn Hittable + Send + Sync>;
+    let wide8 = |list: &HittableList| Arc::new(WideBvh::<8>::new(list)) as Arc<dyn Hittable + Send + Sync>;
+    let configs: [(&str, &BvhBuilder); 5] = [
+        ("median", &median),
+        ("sah", &sah),
+        ("sah flat", &flat),
+        ("sah bvh4", &wide4),
+        ("sah bvh8", &wide8),
+    ];
+    for (name, build) in configs {
+        let start = Instant::now();
+        let (world, mut cam) = final_scene_setup(400, 20, 10, build);
+        cam.initialize();
+        let world = build(&world);
+        let build = start.elapsed();
+
+        let start = Instant::now();
+        render_image(&cam, &world);
+        println!("{}: 建场景和BVH {:.3} 秒, 渲染 {:.3} 秒", name, build.as_secs_f64(), start.elapsed().as_secs_f64());
+    }
+}
+fn transforms() {
+    let mut world = HittableList::default();
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(15.0, 15.0, 15.0)));
+
+    world.add(Arc::new(Quad::new(Point3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), green)));
+    world.add(Arc::new(Quad::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), red)));
+    world.add(Arc::new(Quad::new(Point3::new(343.0, 554.0, 332.0), Vec3::new(-130.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -105.0), light)));
+    world.add(Arc::new(Quad::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 555.0), Arc::clone(&white))));
+    world.add(Arc::new(Quad::new(Point3::new(555.0, 555.0, 555.0), Vec3::new(-555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -555.0), Arc::clone(&white))));
+    world.add(Arc::new(Quad::new(Point3::new(0.0, 0.0, 555.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Arc::clone(&white))));
+
+    // 单位立方体经过缩放、绕X/Z轴旋转后放置
+    let unit_box = make_box(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5), Arc::clone(&white));
+    let m1 = Mat4::translate(Vec3::new(370.0, 200.0, 350.0))
+        * Mat4::rotate_y(-15.0)
+        * Mat4::rotate_x(30.0)
+        * Mat4::rotate_z(20.0)
+        * Mat4::scale(Vec3::new(165.0, 300.0, 165.0));
+    world.add(Arc::new(Transform::new(Arc::clone(&unit_box), m1)));
+
+    // 共享同一个立方体原型，覆盖材质
+    let cube = Prototype::from_hittable(unit_box);
+    let gold: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3));
+    world.add(Arc::new(cube.instance_with_material(
+        Mat4::translate(Vec3::new(430.0, 35.0, 90.0)) * Mat4::rotate_y(30.0) * Mat4::scale(Vec3::new(70.0, 70.0, 70.0)),
+        gold
+    )));
+
+    let ball: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, Arc::new(Metal::new(Color::new(0.8, 0.85, 0.88), 0.0))));
+    let m2 = Mat4::translate(Vec3::new(160.0, 100.0, 180.0))
+        * Mat4::rotate(Vec3::new(1.0, 1.0, 0.0), 45.0)
+        * Mat4::scale(Vec3::new(100.0, 50.0, 70.0));
+    world.add(Arc::new(Transform::new(ball, m2)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 1.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::default();
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(278.0, 278.0, -800.0);
+    cam.lookat = Point3::new(278.0, 278.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn analytic_shapes() {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    let steel: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.75), 0.2));
+    let copper: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.85, 0.5, 0.3), 0.1));
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7)));
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+
+    world.add(Arc::new(Cylinder::new(Point3::new(-4.0, 0.0, 0.0), Point3::new(-4.0, 2.0, 0.0), 0.8, Arc::clone(&steel))));
+    world.add(Arc::new(Cylinder::new_open(Point3::new(-4.0, 0.3, 2.0), Point3::new(-2.5, 0.3, 2.5), 0.3, Arc::clone(&copper))));
+    world.add(Arc::new(Cone::new(Point3::new(-1.5, 0.0, -1.0), Point3::new(-1.5, 2.5, -1.0), 0.9, Arc::clone(&red))));
+    world.add(Arc::new(Cone::new_frustum(Point3::new(0.5, 0.0, 1.5), Point3::new(0.5, 1.2, 1.5), 0.8, 0.4, Arc::clone(&blue))));
+    world.add(Arc::new(Torus::new(Point3::new(2.0, 0.9, -0.5), Vec3::new(0.0, 0.3, 1.0), 0.9, 0.3, Arc::clone(&copper))));
+    world.add(Arc::new(Capsule::new(Point3::new(3.5, 0.5, 1.5), Point3::new(5.0, 1.5, 0.5), 0.5, glass)));
+    world.add(Arc::new(Ellipse::new_disk(Point3::new(-1.0, 0.01, 2.5), Vec3::new(0.0, 1.0, 0.0), 0.7, Arc::clone(&blue))));
+    world.add(Arc::new(Annulus::new_ring(Point3::new(0.5, 3.0, -3.0), Vec3::new(0.0, 0.2, 1.0), 0.6, 1.2, steel)));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 35.0;
+    cam.lookfrom = Point3::new(2.0, 4.0, 12.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn planar_shapes() {
+    let mut world = HittableList::default();
+
+    let left_red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.2, 0.2)));
+    let back_green: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.2, 1.0, 0.2)));
+    let right_blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.2, 0.2, 1.0)));
+    let upper_orange: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.5, 0.0)));
+    let lower_teal: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.2, 0.8, 0.8)));
+
+    world.add(Arc::new(Triangle::new(
+        Point3::new(-3.0, -2.0, 5.0),
+        Vec3::new(0.0, 0.0, -4.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        left_red
+    )));
+    world.add(Arc::new(Ellipse::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.5, 0.0),
+        back_green
+    )));
+    world.add(Arc::new(Annulus::new_ring(
+        Point3::new(3.0, 0.0, 3.0),
+        Vec3::new(-1.0, 0.0, 0.0),
+        1.0,
+        2.0,
+        right_blue
+    )));
+    world.add(make_tetrahedron(
+        Point3::new(-1.5, 2.0, 2.0),
+        Point3::new(0.5, 2.0, 2.0),
+        Point3::new(-0.5, 2.0, 4.0),
+        Point3::new(-0.5, 3.5, 3.0),
+        upper_orange
+    ));
+    world.add(make_pyramid(
+        Point3::new(0.5, -3.0, 2.0),
+        Vec3::new(1.5, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.5),
+        Point3::new(1.25, -1.5, 2.75),
+        lower_teal
+    ));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+
+    cam.vfov = 80.0;
+    cam.lookfrom = Point3::new(0.0, 0.0, 9.0);
+    cam.lookat = Point3::new(0.0, 0.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn csg() {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1)));
+    let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7)));
+    let steel: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.7, 0.7, 0.75), 0.1));
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+
+    // 立方体挖去球
+    let cube: Arc<dyn Solid> = Arc::new(box_sides(Point3::new(-3.5, 0.0, -1.0), Point3::new(-1.5, 2.0, 1.0), Arc::clone(&red)));
+    let ball: Arc<dyn Solid> = Arc::new(Sphere::new(Point3::new(-2.5, 1.0, 0.0), 1.3, Arc::clone(&blue)));
+    world.add(Arc::new(Csg::difference(cube, ball)));
+
+    // 两球相交得到透镜
+    let a: Arc<dyn Solid> = Arc::new(Sphere::new(Point3::new(-0.5, 1.0, 0.0), 1.2, Arc::clone(&glass)));
+    let b: Arc<dyn Solid> = Arc::new(Sphere::new(Point3::new(0.5, 1.0, 0.0), 1.2, glass));
+    world.add(Arc::new(Csg::intersection(a, b)));
+
+    // 带孔的圆柱与小球的并集
+    let body: Arc<dyn Solid> = Arc::new(Cylinder::new(Point3::new(2.5, 0.0, 0.0), Point3::new(2.5, 1.5, 0.0), 1.0, Arc::clone(&steel)));
+    let hole: Arc<dyn Solid> = Arc::new(Cylinder::new(Point3::new(2.5, -0.5, 0.0), Point3::new(2.5, 2.0, 0.0), 0.5, Arc::clone(&red)));
+    let tube: Arc<dyn Solid> = Arc::new(Csg::difference(body, hole));
+    let knob: Arc<dyn Solid> = Arc::new(Sphere::new(Point3::new(2.5, 1.5, 1.0), 0.4, blue));
+    world.add(Arc::new(Csg::union(tube, knob)));
+
+    // 三角网格八面体挖去球，球面从每个面中间穿出
+    let (center, size) = (Point3::new(0.0, 0.9, 2.2), 0.9);
+    let positions = [
+        Vec3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0),
+    ].map(|d| center + size * d).to_vec();
+    let indices = vec![[0, 2, 4], [1, 4, 2], [0, 4, 3], [1, 3, 4], [0, 5, 2], [1, 2, 5], [0, 3, 5], [1, 5, 3]];
+    let octahedron = TriangleMesh::new(positions, indices).into_solid(Arc::clone(&red));
+    let core: Arc<dyn Solid> = Arc::new(Sphere::new(center, 0.6, Arc::clone(&red)));
+    world.add(Arc::new(Csg::difference(octahedron, core)));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 35.0;
+    cam.lookfrom = Point3::new(1.0, 5.0, 9.0);
+    cam.lookat = Point3::new(0.0, 0.8, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn sdf_shapes() {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1)));
+    let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7)));
+    let gold: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    let bbox = |min: Point3, max: Point3| Aabb::new_point(&min, &max);
+
+    // 球与圆角立方体平滑融合
+    let blob: Arc<dyn Sdf> = Arc::new(SdfSmoothUnion::new(
+        Arc::new(SdfSphere::new(Point3::new(-4.0, 1.3, 0.0), 0.6)),
+        Arc::new(SdfBox::new_rounded(Point3::new(-4.0, 0.5, 0.0), Vec3::new(0.6, 0.5, 0.6), 0.1)),
+        0.3,
+    ));
+    world.add(Arc::new(SdfShape::new(blob, bbox(Point3::new(-5.0, 0.0, -1.0), Point3::new(-3.0, 2.0, 1.0)), Arc::clone(&red))));
+
+    // 扭转的长方体
+    let twisted: Arc<dyn Sdf> = Arc::new(SdfTranslate::new(
+        Arc::new(SdfTwist::new(Arc::new(SdfBox::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.4, 1.0, 0.4))), 1.2)),
+        Vec3::new(-2.0, 1.0, 0.0),
+    ));
+    let mut twisted = SdfShape::new(twisted, bbox(Point3::new(-2.6, 0.0, -0.6), Point3::new(-1.4, 2.0, 0.6)), Arc::clone(&blue));
+    twisted.step_scale = 0.5;
+    world.add(Arc::new(twisted));
+
+    // 圆环平滑挖去圆柱
+    let ring: Arc<dyn Sdf> = Arc::new(SdfSmoothSubtract::new(
+        Arc::new(SdfTorus::new(Point3::new(0.0, 0.4, 0.0), 0.8, 0.35)),
+        Arc::new(SdfCylinder::new(Point3::new(0.8, 0.4, 0.0), 0.3, 1.0)),
+        0.1,
+    ));
+    world.add(Arc::new(SdfShape::new(ring, bbox(Point3::new(-1.2, 0.0, -1.2), Point3::new(1.2, 0.8, 1.2)), Arc::clone(&gold))));
+
+    // 弯曲的胶囊
+    let bent: Arc<dyn Sdf> = Arc::new(SdfTranslate::new(
+        Arc::new(SdfBend::new(Arc::new(SdfCapsule::new(Point3::new(-0.8, 0.0, 0.0), Point3::new(0.8, 0.0, 0.0), 0.25)), 0.6)),
+        Vec3::new(2.2, 0.6, 0.0),
+    ));
+    let mut bent = SdfShape::new(bent, bbox(Point3::new(1.0, 0.0, -0.5), Point3::new(3.4, 1.4, 0.5)), Arc::clone(&red));
+    bent.step_scale = 0.7;
+    world.add(Arc::new(bent));
+
+    // 骰子：立方体与球求交，再挖去一个小球
+    let dice: Arc<dyn Sdf> = Arc::new(SdfSubtract::new(
+        Arc::new(SdfIntersect::new(
+            Arc::new(SdfBox::new(Point3::new(4.2, 0.6, 0.0), Vec3::new(0.6, 0.6, 0.6))),
+            Arc::new(SdfSphere::new(Point3::new(4.2, 0.6, 0.0), 0.8)),
+        )),
+        Arc::new(SdfUnion::new(
+            Arc::new(SdfSphere::new(Point3::new(4.2, 1.25, 0.0), 0.2)),
+            Arc::new(SdfSphere::new(Point3::new(4.2, 0.6, 0.65), 0.2)),
+        )),
+    ));
+    world.add(Arc::new(SdfShape::new(dice, bbox(Point3::new(3.5, 0.0, -0.7), Point3::new(4.9, 1.3, 0.7)), white)));
+
+    // 直接用闭包作为距离函数：表面起伏的球
+    let wavy: Arc<dyn Sdf> = Arc::new(|p: Point3| {
+        let q = p - Point3::new(0.0, 1.2, -3.0);
+        q.length() - 1.0 - 0.05 * (8.0 * q.x()).sin() * (8.0 * q.y()).sin() * (8.0 * q.z()).sin()
+    });
+    let mut wavy = SdfShape::new(wavy, bbox(Point3::new(-1.1, 0.1, -4.1), Point3::new(1.1, 2.3, -1.9)), blue);
+    wavy.step_scale = 0.6;
+    world.add(Arc::new(wavy));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 10.0);
+    cam.lookat = Point3::new(0.0, 0.8, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn heightfield() {
+    let mut world = HittableList::default();
+
+    // 用地球贴图的灰度作高度，并把同一张图贴在地形上
+    let earth_texture = Arc::new(ImageTexture::new("earthmap.jpg"));
+    world.add(Arc::new(Heightfield::from_image(
+        "earthmap.jpg",
+        Point3::new(-4.0, 0.0, -3.0),
+        Vec3::new(8.0, 0.6, 4.0),
+        Arc::new(Lambertian::new_texture(earth_texture)),
+    )));
+
+    // Perlin湍流生成的程序化地形
+    let perlin = Perlin::default();
+    let (nx, nz) = (129, 129);
+    let mut heights = Vec::with_capacity(nx * nz);
+    for j in 0..nz {
+        for i in 0..nx {
+            let p = Point3::new(i as f64 / 16.0, 0.0, j as f64 / 16.0);
+            heights.push(perlin.turb(p, 7));
+        }
+    }
+    world.add(Arc::new(Heightfield::new(
+        &heights,
+        nx,
+        nz,
+        Point3::new(-6.0, -1.0, 1.5),
+        Vec3::new(12.0, 1.5, 6.0),
+        Arc::new(Lambertian::new(Color::new(0.4, 0.5, 0.3))),
+    )));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 10.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 6.0, 10.0);
+    cam.lookat = Point3::new(0.0, 0.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn curved_surfaces() {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    // 从面片文件读入的花瓶
+    let vase = tessellate_patches(&load_patches("vase.bpt"), 12);
+    let porcelain: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.85, 0.9), 0.05));
+    world.add(Arc::new(Transform::new(
+        vase.into_hittable(porcelain),
+        Mat4::translate(Vec3::new(-2.5, 0.0, 0.0)),
+    )));
+
+    // 单个弯曲的面板，贴图沿面片参数方向
+    let mut cp = [Point3::zero(); 16];
+    for j in 0..4 {
+        for i in 0..4 {
+            let h = if (1..3).contains(&i) && (1..3).contains(&j) { 1.2 } else { 0.0 };
+            cp[4 * j + i] = Point3::new(i as f64 - 1.5, 0.6 + h, j as f64 - 1.5);
+        }
+    }
+    let panel = BezierPatch::new(cp).tessellate(16);
+    let earth: Arc<dyn Material> = Arc::new(Lambertian::new_texture(Arc::new(ImageTexture::new("earthmap.jpg"))));
+    world.add(panel.into_hittable(earth));
+
+    // 四面体经Loop细分成光滑的团块
+    let tetrahedron = TriangleMesh::new(
+        vec![
+            Point3::new(1.8, 0.2, -0.8),
+            Point3::new(3.0, 0.2, 0.4),
+            Point3::new(1.2, 0.2, 0.8),
+            Point3::new(2.0, 2.2, 0.1),
+        ],
+        vec![[0, 2, 1], [0, 1, 3], [1, 2, 3], [2, 0, 3]],
+    );
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1)));
+    world.add(loop_subdivide(&tetrahedron, 4).into_hittable(Arc::clone(&red)));
+    // 同一个四面体用Catmull-Clark细分，对比两种格式的形状
+    world.add(Arc::new(Transform::new(
+        catmull_clark_subdivide(&PolyMesh::from_triangles(&tetrahedron), 3).into_hittable(red),
+        Mat4::translate(Vec3::new(-1.0, 0.0, -3.0)),
+    )));
+
+    // 立方体经Catmull-Clark细分
+    let positions = (0..8)
+        .map(|k| Point3::new(0.5 * (k & 1) as f64, 0.5 * ((k >> 1) & 1) as f64, 0.5 * ((k >> 2) & 1) as f64))
+        .collect();
+    let faces = vec![
+        vec![0, 2, 3, 1],
+        vec![4, 5, 7, 6],
+        vec![0, 1, 5, 4],
+        vec![2, 6, 7, 3],
+        vec![0, 4, 6, 2],
+        vec![1, 3, 7, 5],
+    ];
+    let cube = catmull_clark_subdivide(&PolyMesh::new(positions, faces), 3);
+    let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7)));
+    world.add(Arc::new(Transform::new(
+        cube.into_hittable(blue),
+        Mat4::translate(Vec3::new(0.0, 0.0, 2.0)) * Mat4::scale(Vec3::new(2.0, 2.0, 2.0)),
+    )));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 10.0);
+    cam.lookat = Point3::new(0.0, 0.8, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn hair_and_grass() {
+    let mut world = HittableList::default();
+
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new(Color::new(0.35, 0.25, 0.15)))
+    )));
+
+    // 草地：宽度从根部到尖端递减的带子，法线随叶片弯曲扭转
+    let grass: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.2, 0.5, 0.1)));
+    for _ in 0..3000 {
+        let root = Point3::new(random_double_range(-6.0, 6.0), 0.0, random_double_range(-3.0, 3.0));
+        let height = random_double_range(0.4, 0.9);
+        let lean = Vec3::new(random_double_range(-0.3, 0.3), 0.0, random_double_range(-0.3, 0.3));
+        let cp = [
+            root,
+            root + Vec3::new(0.0, height / 3.0, 0.0),
+            root + Vec3::new(0.0, 2.0 * height / 3.0, 0.0) + 0.5 * lean,
+            root + Vec3::new(0.0, height, 0.0) + lean,
+        ];
+        let n0 = Vec3::new(random_double_range(-1.0, 1.0), 0.0, random_double_range(-1.0, 1.0));
+        let n1 = n0 + Vec3::new(0.0, 0.5, 0.0);
+        world.add(Arc::new(Curve::new(cp, 0.06, 0.005, CurveType::Ribbon { n0, n1 }, Arc::clone(&grass))));
+    }
+
+    // 毛球：从球面向外长出的毛发
+    let center = Point3::new(-1.5, 1.3, 0.0);
+    let hair: Arc<dyn Material> = Arc::new(Hair::from_melanin(1.3, 0.2, 0.25, 0.3));
+    world.add(Arc::new(Sphere::new(center, 0.8, Arc::clone(&hair))));
+    for _ in 0..4000 {
+        let n = Vec3::random_unit_vector();
+        let root = center + 0.8 * n;
+        let droop = Vec3::new(0.0, -0.25, 0.0) + 0.1 * Vec3::random_unit_vector();
+        let cp = [root, root + 0.15 * n, root + 0.3 * n + 0.5 * droop, root + 0.4 * n + droop];
+        world.add(Arc::new(Curve::new(cp, 0.008, 0.002, CurveType::Cylinder, Arc::clone(&hair))));
+    }
+
+    // 一根长电缆，拆成多段放入BVH
+    let cable: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.5, 0.3), 0.3));
+    let cp = [
+        Point3::new(0.5, 0.1, 1.0),
+        Point3::new(1.5, 3.0, -2.0),
+        Point3::new(3.0, -1.0, 0.0),
+        Point3::new(4.0, 2.0, 0.5),
+    ];
+    let segments = Curve::new_split(cp, 0.12, 0.12, CurveType::Cylinder, 16, cable);
+    world.add(Arc::new(BvhNode::new(&segments)));
+
+    // 朝向光线的平带
+    let blond: Arc<dyn Material> = Arc::new(Hair::from_color(Color::new(0.8, 0.6, 0.3), 0.3, 0.3));
+    for i in 0..40 {
+        let x = 1.0 + 0.02 * i as f64;
+        let cp = [
+            Point3::new(x, 2.6, -1.0),
+            Point3::new(x + 0.3, 2.2, -1.0),
+            Point3::new(x - 0.2, 1.8, -1.0),
+            Point3::new(x + 0.1, 1.2, -1.0),
+        ];
+        world.add(Arc::new(Curve::new(cp, 0.015, 0.01, CurveType::Flat, Arc::clone(&blond))));
+    }
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 3.0, 8.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn point_clouds() {
+    let mut world = HittableList::default();
+
+    let vertex_color: Arc<dyn Material> = Arc::new(VertexColor::new(Color::new(0.5, 0.5, 0.5)));
+
+    // 一百万个点组成的Perlin地形，按高度着色，渲染为朝向光线的圆片
+    let perlin = Perlin::default();
+    let n = 1000;
+    let mut positions = Vec::with_capacity(n * n);
+    let mut colors = Vec::with_capacity(n * n);
+    for j in 0..n {
+        for i in 0..n {
+            let x = -8.0 + 16.0 * (i as f64 + random_double()) / n as f64;
+            let z = -6.0 + 8.0 * (j as f64 + random_double()) / n as f64;
+            let h = perlin.turb(Point3::new(0.4 * x, 0.0, 0.4 * z), 7);
+            positions.push([x as f32, (1.2 * h) as f32, z as f32]);
+            let c = if h < 0.25 {
+                [40, 90, 160]
+            } else if h < 0.5 {
+                [60, 140, 50]
+            } else {
+                [150, 120, 80]
+            };
+            colors.push(c);
+        }
+    }
+    world.add(Arc::new(PointCloud::new(positions, Vec::new(), colors, 0.012, PointShape::Disk, Arc::clone(&vertex_color))));
+
+    // 带法线的扫描数据，渲染为定向圆片
+    world.add(Arc::new(Transform::new(
+        Arc::new(PointCloud::load_xyz("knot.xyz", 0.045, PointShape::Disk, Arc::clone(&vertex_color))),
+        Mat4::translate(Vec3::new(-1.5, 2.2, 0.0)),
+    )));
+    // 同一份数据渲染为小球
+    world.add(Arc::new(Transform::new(
+        Arc::new(PointCloud::load_xyz("knot.xyz", 0.04, PointShape::Sphere, vertex_color)),
+        Mat4::translate(Vec3::new(1.8, 2.2, 0.0)),
+    )));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 10.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
+    render(cam,&world);
+}
+fn motion_blur() {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1)));
+    let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7)));
+    let gold: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
+    let earth: Arc<dyn Material> = Arc::new(Lambertian::new_texture(Arc::new(ImageTexture::new("earthmap.jpg"))));
+    let y_axis = Vec3::new(0.0, 1.0, 0.0);
+
+    // 自带运动的球，法线随球心移动
+    world.add(Arc::new(Sphere::new_center2(
+        Point3::new(-3.2, 0.6, 0.0),
+        Point3::new(-3.2, 1.4, 0.0),
+        0.6,
+        Arc::clone(&gold),
+    )));
+
+    // 沿曲线经过多个关键帧平移的立方体
+    world.add(Arc::new(MotionTransform::new(
+        make_box(Point3::new(-0.4, 0.0, -0.4), Point3::new(0.4, 0.8, 0.4), Arc::clone(&red)),
+        vec![
+            Keyframe::translation(0.0, Vec3::new(-2.2, 0.0, 0.0)),
+            Keyframe::translation(0.5, Vec3::new(-1.7, 0.6, 0.0)),
+            Keyframe::translation(1.0, Vec3::new(-1.2, 0.0, 0.0)),
+        ],
+    )));
+
+    // 自转的地球
+    world.add(Arc::new(MotionTransform::new(
+        Arc::new(Sphere::new(Point3::zero(), 1.0, earth)),
+        vec![
+            Keyframe::new(0.0, Vec3::new(0.5, 1.0, 0.0), Quat::identity(), Vec3::ones()),
+            Keyframe::new(1.0, Vec3::new(0.5, 1.0, 0.0), Quat::from_axis_angle(y_axis, 60.0), Vec3::ones()),
+        ],
+    )));
+
+    // 旋转并放大的圆环
+    world.add(Arc::new(MotionTransform::new(
+        Arc::new(Torus::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 0.6, 0.2, blue)),
+        vec![
+            Keyframe::new(0.0, Vec3::new(2.6, 1.0, 0.0), Quat::identity(), Vec3::new(0.8, 0.8, 0.8)),
+            Keyframe::new(1.0, Vec3::new(2.6, 1.0, 0.0), Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), 90.0), Vec3::new(1.2, 1.2, 1.2)),
+        ],
+    )));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 10.0);
+    cam.lookat = Point3::new(0.0, 0.8, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+    // 只曝光前3/4的运动过程
+    cam.shutter_open = 0.0;
+    cam.shutter_close = 0.75;
+
+    render(cam,&world);
+}
+fn turntable(first_frame: u32, last_frame: u32) {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1)));
+    let gold: Arc<dyn Material> = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Cylinder::new(Point3::new(-1.5, 0.0, 0.0), Point3::new(-1.5, 1.5, 0.0), 0.5, red)));
+    world.add(Arc::new(Sphere::new(Point3::new(1.5, 0.7, 0.0), 0.7, glass)));
+
+    // 物体动画与相机使用同一时间轴（秒）：圆环在1秒内转半圈并上下跳动
+    world.add(Arc::new(MotionTransform::new(
+        Arc::new(Torus::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 0.6, 0.2, gold)),
+        vec![
+            Keyframe::new(0.0, Vec3::new(0.0, 0.8, -1.5), Quat::identity(), Vec3::ones()),
+            Keyframe::new(0.5, Vec3::new(0.0, 1.6, -1.5), Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 90.0), Vec3::ones()),
+            Keyframe::new(1.0, Vec3::new(0.0, 0.8, -1.5), Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 180.0), Vec3::ones()),
+        ],
+    )));
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 200;
+    cam.samples_per_pixel = 20;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 3.0, 8.0);
+    cam.lookat = Point3::new(0.0, 0.8, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+
+    // 1秒转一圈，最后再推近镜头并打开景深
+    let mut keys = CameraTrack::turntable(&cam, 0.0, 1.0, 8).keys().to_vec();
+    let mut close_up = CameraKey::from_camera(1.5, &cam);
+    close_up.lookfrom = Point3::new(0.0, 1.5, 4.0);
+    close_up.vfov = 30.0;
+    close_up.focus_dist = 4.0;
+    close_up.defocus_angle = 2.0;
+    keys.push(close_up);
+    let track = CameraTrack::new(keys);
+
+    let sequence = Sequence {
+        first_frame,
+        last_frame,
+        fps: 24.0,
+        shutter: 0.5,
+        ..Sequence::default()
+    };
+    render_sequence(cam, &track, &world, &sequence);
+}
+// 1: 正交 2: 鱼眼 3: 全景 4: 全景上下双目
+fn projections(mode: u32) {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    // 围成一圈的物体，全景时四周都能看到
+    let colors = [
+        Color::new(0.7, 0.15, 0.1),
+        Color::new(0.1, 0.2, 0.7),
+        Color::new(0.8, 0.6, 0.2),
+        Color::new(0.2, 0.6, 0.2),
+        Color::new(0.6, 0.2, 0.6),
+        Color::new(0.2, 0.6, 0.6),
+    ];
+    for (k, c) in colors.iter().enumerate() {
+        let angle = 2.0 * util::PI * k as f64 / colors.len() as f64;
+        let p = Point3::new(4.0 * angle.sin(), 0.0, -4.0 * angle.cos());
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(*c));
+        if k % 2 == 0 {
+            world.add(make_box(p - Vec3::new(0.6, 0.0, 0.6), p + Vec3::new(0.6, 1.2, 0.6), mat));
+        } else {
+            world.add(Arc::new(Sphere::new(p + Vec3::new(0.0, 0.8, 0.0), 0.8, mat)));
+        }
+    }
+
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 0.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 50;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 1.0, 0.0);
+    cam.lookat = Point3::new(0.0, 1.0, -1.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+
+    match mode {
+        1 => {
+            // 斜上方的正交视图
+            cam.lookfrom = Point3::new(6.0, 6.0, 6.0);
+            cam.lookat = Point3::new(0.0, 0.0, 0.0);
+            cam.projection = Projection::Orthographic { height: 10.0 };
+            render(cam, &world);
+        }
+        2 => {
+            // 图像高度固定为450，用宽度得到方形画幅
+            cam.image_width = 450;
+            cam.projection = Projection::Fisheye { fov: 180.0 };
+            render(cam, &world);
+        }
+        3 => {
+            // 全景需要2:1的画幅
+            cam.image_width = 900;
+            cam.projection = Projection::Equirectangular;
+            render(cam, &world);
+        }
+        _ => {
+            cam.image_width = 900;
+            cam.projection = Projection::Equirectangular;
+            render_stereo(cam, &world, 0.064, StereoLayout::OverUnder);
+        }
+    }
+}
+// 1: 六边形光圈叶片加猫眼渐晕 2: 心形散景 3: 移轴微缩
+fn bokeh(mode: u32) {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    // 前景对焦的主体
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)))));
+    world.add(Arc::new(Sphere::new(Point3::new(-2.5, 0.7, 1.5), 0.7, Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1))))));
+    world.add(Arc::new(Sphere::new(Point3::new(2.5, 0.7, -1.5), 0.7, Arc::new(Dielectric::new(1.5)))));
+
+    // 远处的小光点，离焦后成为散景
+    for k in 0..60 {
+        let p = Point3::new(random_double_range(-25.0, 25.0), random_double_range(1.0, 12.0), random_double_range(-45.0, -30.0));
+        let c = Color::new(random_double_range(0.5, 1.0), random_double_range(0.3, 0.8), random_double_range(0.2, 0.6));
+        let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(20.0 * c));
+        world.add(Arc::new(Sphere::new(p, 0.15 + 0.05 * (k % 3) as f64, light)));
+    }
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 200;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.02, 0.02, 0.05);
+
+    cam.vfov = 30.0;
+    cam.lookfrom = Point3::new(0.0, 2.0, 10.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+    cam.focus_dist = 10.0;
+    cam.defocus_angle = 2.5;
+
+    match mode {
+        1 => {
+            cam.aperture = Aperture::Blades { count: 6, rotation: 15.0 };
+            cam.cat_eye = 0.8;
+        }
+        2 => {
+            cam.aperture = Aperture::Mask(Arc::new(ApertureMask::from_image("heart.ppm")));
+        }
+        _ => {
+            // 俯视场景，焦平面反向倾斜，只有中间一条横带清晰
+            cam.background = Color::new(0.7, 0.8, 1.0);
+            cam.lookfrom = Point3::new(0.0, 8.0, 12.0);
+            cam.lookat = Point3::new(0.0, 0.0, 0.0);
+            cam.focus_dist = (cam.lookfrom - cam.lookat).length();
+            cam.defocus_angle = 4.0;
+            cam.focus_tilt = -40.0;
+        }
+    }
+
+    render(cam, &world);
+}
+// 物理曝光：三组参数的EV相近，画面亮度一致，景深和运动模糊不同
+// 1: f/8 1/250s ISO 6400 2: f/2 1/30s ISO 100 3: 同2，曝光补偿-1EV
+fn physical_exposure(mode: u32) {
+    let mut world = HittableList::default();
+
+    let checker: Arc<dyn Texture + Send + Sync> = Arc::new(
+        CheckerTexture::new_color(0.5, Color::new(0.2, 0.3, 0.1), Color::new(0.9, 0.9, 0.9))
+    );
+    world.add(Arc::new(Plane::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new_texture(checker))
+    )));
+
+    // 一排球体展示景深
+    for k in 0..6 {
+        let z = 2.0 - 3.0 * k as f64;
+        let c = Color::new(0.2 + 0.1 * k as f64, 0.3, 0.8 - 0.1 * k as f64);
+        world.add(Arc::new(Sphere::new(Point3::new(-1.5, 0.5, z), 0.5, Arc::new(Lambertian::new(c)))));
+    }
+    // 以20m/s横穿画面的球，时间以秒计
+    world.add(Arc::new(Sphere::new_center2(
+        Point3::new(-10.0, 0.6, 0.0),
+        Point3::new(10.0, 0.6, 0.0),
+        0.6,
+        Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)),
+    )));
+
+    // 亮度单位为cd/m²：阴天室外的天空与一盏灯
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(2000.0, 1900.0, 1700.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(3.0, 6.0, 3.0), 1.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 100;
+    cam.max_depth = 10;
+    cam.background = Color::new(120.0, 140.0, 170.0);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(2.0, 1.5, 6.0);
+    cam.lookat = Point3::new(0.0, 0.6, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+    cam.focus_dist = (cam.lookfrom - cam.lookat).length();
+    cam.shutter_open = 0.5;
+
+    let mut exposure = PhysicalExposure::default();
+    match mode {
+        1 => {
+            exposure.f_number = 8.0;
+            exposure.shutter_time = 1.0 / 250.0;
+            exposure.iso = 6400.0;
+        }
+        _ => {
+            exposure.f_number = 2.0;
+            exposure.shutter_time = 1.0 / 30.0;
+            exposure.iso = 100.0;
+            if mode == 3 {
+                exposure.exposure_compensation = -1.0;
+            }
+        }
+    }
+    println!("EV100 = {:.2}", exposure.ev100());
+    cam.exposure = Some(exposure);
+
+    render(cam, &world);
+}
+// 约一百万个三角形的起伏网格，统计建网格、建BVH和渲染的耗时
+fn packet_benchmark() {
+    // 主光线相干的场景：地面、成排的小球和一块起伏的三角网格
+    let mut list = HittableList::default();
+    let ground: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    list.add(Arc::new(Quad::new(Point3::new(-20.0, 0.0, -20.0), Vec3::new(40.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 40.0), ground)));
+    for a in -10..10 {
+        for b in -10..10 {
+            let center = Point3::new(a as f64 + 0.5, 0.3, b as f64 + 0.5);
+            let albedo = Color::random() * Color::random();
+            list.add(Arc::new(Sphere::new(center, 0.3, Arc::new(Lambertian::new(albedo)))));
+        }
+    }
+    let n = 101;
+    let mut positions = Vec::with_capacity(n * n);
+    for j in 0..n {
+        for i in 0..n {
+            let x = -4.0 + 8.0 * i as f64 / (n - 1) as f64;
+            let y = 2.0 + 4.0 * j as f64 / (n - 1) as f64;
+            positions.push(Point3::new(x, y, -6.0 + 0.3 * (2.0 * x).sin() * (3.0 * y).cos()));
+        }
+    }
+    let mut indices = Vec::with_capacity(2 * (n - 1) * (n - 1));
+    for j in 0..n - 1 {
+        for i in 0..n - 1 {
+            let k = j * n + i;
+            indices.push([k, k + 1, k + n]);
+            indices.push([k + 1, k + n + 1, k + n]);
+        }
+    }
+    let mut mesh = TriangleMesh::new(positions, indices);
+    mesh.compute_smooth_normals();
+    list.add(mesh.into_hittable(Arc::new(Metal::new(Color::new(0.8, 0.6, 0.3), 0.1))));
+    // 与render相同的默认加速结构，随bvh4/bvh8特性切换
+    let world = BvhNode::new_boxed(&list);
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 20;
+    cam.max_depth = 4;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.vfov = 50.0;
+    cam.lookfrom = Point3::new(0.0, 4.0, 10.0);
+    cam.lookat = Point3::new(0.0, 1.5, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.initialize();
+
+    for packet_size in [0, 4, 8, 16] {
+        cam.packet_size = packet_size;
+        let start = Instant::now();
+        render_image(&cam, &world);
+        println!("光线包大小 {}: 渲染 {:.3} 秒", packet_size, start.elapsed().as_secs_f64());
+    }
+}
+// 喷泉：小球从原点喷出、落地反弹，每帧移走最早的几个并喷出新的；
+// 场景用动态BVH逐帧refit，树的代价退化到一定程度时自动重建
+fn dynamic_scene(frames: u32) {
+    struct Particle {
+        id: ObjectId,
+        pos: Point3,
+        vel: Vec3,
+        mat: Arc<dyn Material>,
+    }
+    let radius = 0.12;
+    let ball = |pos: Point3, mat: &Arc<dyn Material>| -> Arc<dyn Hittable> { Arc::new(Sphere::new(pos, radius, Arc::clone(mat))) };
+    // 新喷出的小球：位置、速度和材质
+    let spawn = || {
+        let pos = Point3::new(random_double_range(-0.1, 0.1), radius, random_double_range(-0.1, 0.1));
+        let vel = Vec3::new(random_double_range(-1.5, 1.5), random_double_range(4.0, 7.0), random_double_range(-1.5, 1.5));
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::random() * Color::random()));
+        (pos, vel, mat)
+    };
+
+    let initial: Vec<_> = (0..400).map(|_| spawn()).collect();
+    let mut list = HittableList::default();
+    for (pos, _, mat) in &initial {
+        list.add(ball(*pos, mat));
+    }
+    let (bvh, ids) = DynamicBvh::new(&list);
+    let mut particles: Vec<Particle> = initial
+        .into_iter()
+        .zip(ids)
+        .map(|((pos, vel, mat), id)| Particle { id, pos, vel, mat })
+        .collect();
+    let mut dynamic = Arc::new(bvh);
+
+    let ground: Arc<dyn Hittable> = Arc::new(Plane::new(
+        Point3::zero(),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    ));
+
+    let mut cam = Camera::default();
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 200;
+    cam.samples_per_pixel = 10;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.7, 0.8, 1.0);
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 3.0, 10.0);
+    cam.lookat = Point3::new(0.0, 1.5, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+    cam.initialize();
+
+    let sequence = Sequence { output_dir: String::from("output/dynamic"), ..Sequence::default() };
+    std::fs::create_dir_all(&sequence.output_dir).unwrap();
+    let dt = 1.0 / sequence.fps;
+    for frame in 1..=frames {
+        let bvh = Arc::get_mut(&mut dynamic).unwrap();
+        let start = Instant::now();
+        for particle in particles.drain(..10) {
+            bvh.remove(particle.id);
+        }
+        for _ in 0..10 {
+            let (pos, vel, mat) = spawn();
+            let id = bvh.insert(ball(pos, &mat));
+            particles.push(Particle { id, pos, vel, mat });
+        }
+        for particle in particles.iter_mut() {
+            particle.vel.y -= 9.8 * dt;
+            particle.pos += particle.vel * dt;
+            if particle.pos.y() < radius {
+                particle.pos.y = radius;
+                particle.vel.y = -0.6 * particle.vel.y();
+            }
+            bvh.update(particle.id, ball(particle.pos, &particle.mat));
+        }
+        let rebuilt = bvh.refit();
+        println!(
+            "第{}帧：更新 {:.3} 毫秒，SAH代价 {:.2}{}",
+            frame,
+            start.elapsed().as_secs_f64() * 1000.0,
+            bvh.cost(),
+            if rebuilt { "（已重建）" } else { "" }
+        );
+
+        let mut world = HittableList::default();
+        world.add(Arc::clone(&ground));
+        world.add(dynamic.clone());
+        let world: Arc<dyn Hittable + Send + Sync> = Arc::new(world);
+        let img = render_image(&cam, &world);
+        let path = sequence.frame_path(frame);
+        match img.save(&path) {
+            Ok(_) => println!("Ouput image as \"{}\"", path),
+            Err(_) => println!("Outputting image fails."),
+        }
+    }
+}
+fn million_triangles() {
+    let n = 709;
+    let start = Instant::now();
+    let mut positions = Vec::with_capacity(n * n);
+    for j in 0..n {
+        for i in 0..n {
+            let x = -5.0 + 10.0 * i as f64 / (n - 1) as f64;
+            let z = -5.0 + 10.0 * j as f64 / (n - 1) as f64;
+            let y = 0.3 * (3.0 * x).sin() * (3.0 * z).cos() + 0.05 * (17.0 * x + 11.0 * z).sin();
+            positions.push(Point3::new(x, y, z));
+        }
+    }
+    let mut indices = Vec::with_capacity(2 * (n - 1) * (n - 1));
+    for j in 0..n - 1 {
+        for i in 0..n - 1 {
+            let k = j * n + i;
+            indices.push([k, k + n, k + 1]);
+            indices.push([k + 1, k + n, k + n + 1]);
+        }
+    }
+    let mut mesh = TriangleMesh::new(positions, indices);
+    mesh.compute_smooth_normals();
+    let triangles = mesh.indices.len();
+    println!("{} 个三角形，建网格 {:.3} 秒", triangles, start.elapsed().as_secs_f64());
+
+    let start = Instant::now();
+    let surface = mesh.into_hittable(Arc::new(Metal::new(Color::new(0.8, 0.6, 0.3), 0.2)));
+    println!("建BVH {:.3} 秒", start.elapsed().as_secs_f64());
+
+    let mut world = HittableList::default();
+    world.add(surface);
+    let light: Arc<dyn Material> = Arc::new(DiffuseLight::new_with_color(Color::new(6.0, 6.0, 6.0)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 8.0, 4.0), 2.0, light)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 20;
+    cam.max_depth = 10;
+    cam.background = Color::new(0.4, 0.5, 0.7);
+
+    cam.vfov = 40.0;
+    cam.lookfrom = Point3::new(0.0, 5.0, 10.0);
+    cam.lookat = Point3::new(0.0, 0.0, 0.0);
+    cam.vup = vec3::Vec3::new(0.0, 1.0, 0.0);
+    cam.defocus_angle = 0.0;
+
+    render(cam, &world);
+}
+// 环境光遮蔽：不看材质和光源，只统计交点附近被几何体挡住的程度，常用于检查模型的接缝和缝隙
+fn ambient_occlusion() {
+    let mut world = HittableList::default();
+    let white: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+
+    world.add(Arc::new(Plane::new(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), Arc::clone(&white))));
+    world.add(Arc::new(Cylinder::new(Point3::new(-4.0, 0.0, 0.0), Point3::new(-4.0, 2.0, 0.0), 0.8, Arc::clone(&white))));
+    world.add(Arc::new(Cone::new(Point3::new(-1.5, 0.0, -1.0), Point3::new(-1.5, 2.5, -1.0), 0.9, Arc::clone(&white))));
+    world.add(Arc::new(Torus::new(Point3::new(2.0, 0.9, -0.5), Vec3::new(0.0, 0.3, 1.0), 0.9, 0.3, Arc::clone(&white))));
+    world.add(Arc::new(Capsule::new(Point3::new(3.5, 0.5, 1.5), Point3::new(5.0, 1.5, 0.5), 0.5, Arc::clone(&white))));
+    world.add(Arc::new(RotateY::new(Arc::new(box_sides(Point3::new(-0.5, 0.0, 1.0), Point3::new(1.0, 1.5, 2.5), Arc::clone(&white))), 20.0)));
+    // 一堆小球挤在一起，球与球、球与地面的缝隙最暗
+    for i in 0..12 {
+        let a = i as f64 * util::PI / 6.0;
+        world.add(Arc::new(Sphere::new(Point3::new(0.3 + 0.7 * a.cos(), 0.3, 4.0 + 0.7 * a.sin()), 0.3, Arc::clone(&white))));
+    }
+    world.add(Arc::new(Sphere::new(Point3::new(0.3, 0.8, 4.0), 0.5, white)));
+
+    let mut cam = Camera::default();
+
+    cam.aspect_ratio = 16.0 / 9.0;
+    cam.image_width = 400;
+    cam.samples_per_pixel = 32;
+    cam.max_depth = 1;
+    cam.background = Color::ones();
+    cam.integrator = Integrator::AmbientOcclusion { samples: 4, distance: 2.0 };
+
+    cam.vfov = 35.0;
+    cam.lookfrom = Point3::new(2.0, 4.0, 12.0);
+    cam.lookat = Point3::new(0.0, 1.0, 0.0);
+    cam.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    cam.defocus_angle = 0.0;
+
     render(cam,&world);
-    // cam.render(&world);
-    
 }
+
 fn main() {
     let now = Instant::now();
     match 3 {
@@ -687,6 +1949,25 @@ fn main() {
         7 => cornell_box(),
         8 => cornell_smoke(),
         9 => final_scene(800, 100, 10),
+        10 => transforms(),
+        11 => analytic_shapes(),
+        12 => planar_shapes(),
+        13 => csg(),
+        14 => sdf_shapes(),
+        15 => heightfield(),
+        16 => curved_surfaces(),
+        17 => hair_and_grass(),
+        18 => point_clouds(),
+        19 => motion_blur(),
+        20 => turntable(1, 36),
+        21 => projections(1),
+        22 => bokeh(1),
+        23 => physical_exposure(1),
+        24 => bvh_benchmark(),
+        25 => million_triangles(),
+        26 => packet_benchmark(),
+        27 => dynamic_scene(48),
+        28 => ambient_occlusion(),
         _ => (),
     }
     let end = now.elapsed().as_secs();
@@ -697,73 +1978,149 @@ pub fn render(mut camera:  Camera, world: &HittableList) {
 
     let path = "output/test.jpg";
     let quality = 60;
+    let world = BvhNode::new_boxed(world);
+    let img = render_image(&camera, &world);
+
+    println!("Ouput image as \"{}\"\n Author: {}", path, AUTHOR);
+    let output_image: image::DynamicImage = image::DynamicImage::ImageRgb8(img);
+    let mut output_file: File = File::create(path).unwrap();
+    match output_image.write_to(&mut output_file, image::ImageOutputFormat::Jpeg(quality)) {
+        Ok(_) => {}
+        Err(_) => println!("Outputting image fails."),
+    }
+}
+
+// 分别渲染左右眼，按layout拼成一张图输出
+pub fn render_stereo(camera: Camera, world: &HittableList, separation: f64, layout: StereoLayout) {
+    let path = "output/test.jpg";
+    let quality = 60;
+    let world = BvhNode::new_boxed(world);
+
+    let (mut left, mut right) = camera.stereo_pair(separation);
+    left.initialize();
+    right.initialize();
+    let left_img = render_image(&left, &world);
+    let right_img = render_image(&right, &world);
+
+    let (w, h) = (left.image_width, left.image_height);
+    let (ox, oy) = match layout {
+        StereoLayout::SideBySide => (w, 0),
+        StereoLayout::OverUnder => (0, h),
+    };
+    let mut img: RgbImage = ImageBuffer::new(w + ox, h + oy);
+    for (x, y, pixel) in left_img.enumerate_pixels() {
+        img.put_pixel(x, y, *pixel);
+    }
+    for (x, y, pixel) in right_img.enumerate_pixels() {
+        img.put_pixel(x + ox, y + oy, *pixel);
+    }
+
+    println!("Ouput image as \"{}\"\n Author: {}", path, AUTHOR);
+    let output_image: image::DynamicImage = image::DynamicImage::ImageRgb8(img);
+    let mut output_file: File = File::create(path).unwrap();
+    match output_image.write_to(&mut output_file, image::ImageOutputFormat::Jpeg(quality)) {
+        Ok(_) => {}
+        Err(_) => println!("Outputting image fails."),
+    }
+}
+
+// 按相机轨道逐帧渲染，输出 frame_0001.png 这样编号的图片；BVH只建一次，物体的运动由光线时间决定
+pub fn render_sequence(camera: Camera, track: &CameraTrack, world: &HittableList, sequence: &Sequence) {
+    let world = BvhNode::new_boxed(world);
+    std::fs::create_dir_all(&sequence.output_dir).unwrap();
+    if track.is_empty() {
+        println!("相机没有关键帧，所有帧使用同一视角");
+    }
+
+    for frame in sequence.first_frame..=sequence.last_frame {
+        let mut cam = sequence.camera_for_frame(&camera, track, frame);
+        cam.initialize();
+        println!("渲染第{}帧 (t = {:.3})", frame, sequence.frame_time(frame));
+        let img = render_image(&cam, &world);
+
+        let path = sequence.frame_path(frame);
+        match img.save(&path) {
+            Ok(_) => println!("Ouput image as \"{}\"", path),
+            Err(_) => println!("Outputting image fails."),
+        }
+    }
+}
+
+// 多线程渲染一张图，相机须已initialize
+fn render_image(camera: &Camera, world: &Arc<dyn Hittable + Send + Sync>) -> RgbImage {
     let bar: Arc<ProgressBar> = if Camera::is_ci() {
         Arc::new(ProgressBar::hidden())
     } else {
         Arc::new(ProgressBar::new((camera.image_height * camera.image_width) as u64))
     };
     let img = Arc::new(Mutex::new(ImageBuffer::new(
-        camera.image_width.try_into().unwrap(),
-        camera.image_height.try_into().unwrap(),
+        camera.image_width,
+        camera.image_height,
     )));
 
-    // let mut img: RgbImage = ImageBuffer::new(self.image_width, self.image_height);
-    
     let mut handles = vec![];
     let thread_num = 10;
     println!("使用{}条线程渲染", thread_num);
-    let world = BvhNode::new_boxed(world);
 
-    let cam_ =  Arc::new(Mutex::new(camera));
+    let cam_ = Arc::new(camera.clone());
 
     for k in 0..thread_num {
         let cam = Arc::clone(&cam_);
         let world = world.clone();
         let img = img.clone();
         let bar = bar.clone();
-        // let background_ = cam.background;
-        
+
         let handle = thread::spawn(move || {
-            let cam = cam.lock().unwrap();
-            for j in (k * cam.image_height/ thread_num)..((k + 1) * cam.image_height / thread_num) {
+            let (row_begin, row_end) = (k * cam.image_height / thread_num, (k + 1) * cam.image_height / thread_num);
+            let put_pixel = |mut color_vec: Color, i: u32, j: u32| {
+                color_vec *= cam.exposure_scale();
+                color_vec.x = linear_to_gamma(color_vec.x);
+                color_vec.y = linear_to_gamma(color_vec.y);
+                color_vec.z = linear_to_gamma(color_vec.z);
+                let pixel_color = [
+                    (color_vec.x * 255.999) as u8,
+                    (color_vec.y * 255.999) as u8,
+                    (color_vec.z * 255.999) as u8,
+                ];
+                write_color(pixel_color, &mut img.lock().unwrap(), i as usize, j as usize);
+                bar.inc(1);
+            };
+            if cam.packet_size > 0 {
+                // 按像素块追踪主光线包，块在本线程负责的行范围内截断
+                let (tile_w, tile_h) = cam.packet_tile();
+                for j in (row_begin..row_end).step_by(tile_h as usize) {
+                    let h = tile_h.min(row_end - j);
+                    for i in (0..cam.image_width).step_by(tile_w as usize) {
+                        let w = tile_w.min(cam.image_width - i);
+                        let colors = cam.sample_tile(&*world, i, j, w, h);
+                        for (index, color) in colors.into_iter().enumerate() {
+                            put_pixel(color, i + index as u32 % w, j + index as u32 / w);
+                        }
+                    }
+                }
+                return;
+            }
+            for j in row_begin..row_end {
                 for i in 0..cam.image_width {
-                    let mut rng: rand::rngs::ThreadRng = rand::thread_rng();
-            let pixel_center = cam.pixel00_loc + i as f64 * cam.pixel_delta_u + j as f64 * cam.pixel_delta_v;
-            let ray_direction = pixel_center - cam.center;
-            let r = Ray::new(cam.center, ray_direction);
-            let mut color_vec = Vec3::zero();
-            for _ in 0..cam.samples_per_pixel {
-                let r = cam.get_ray(i, j);
-                color_vec += Camera::ray_color(&*&cam,&r,cam.max_depth, &*world)/cam.samples_per_pixel as f64;
+                    let mut color_vec = Vec3::zero();
+                    for _ in 0..cam.samples_per_pixel {
+                        if let Some(r) = cam.get_ray(i, j) {
+                            color_vec += Camera::ray_color(&cam, &r, cam.max_depth, &*world) / cam.samples_per_pixel as f64;
+                        }
+                    }
+                    put_pixel(color_vec, i, j);
+                }
             }
-            color_vec.x = linear_to_gamma(color_vec.x);
-            color_vec.y = linear_to_gamma(color_vec.y);
-            color_vec.z = linear_to_gamma(color_vec.z);
-            let pixel_color = [
-                (color_vec.x * 255.999) as u8,
-                (color_vec.y * 255.999) as u8,
-                (color_vec.z * 255.999) as u8,
-            ];
-            write_color(pixel_color, &mut img.lock().unwrap(), i as usize, j as usize);
-            bar.inc(1);
-        }
+        });
+        handles.push(handle);
     }
-    });
-    handles.push(handle);
-}
 
     for handle in handles {
         handle.join().unwrap();
     }
 
     bar.finish();
-    println!("Ouput image as \"{}\"\n Author: {}", path, AUTHOR);
-    let output_image: image::DynamicImage = image::DynamicImage::ImageRgb8(Mutex::into_inner(Arc::into_inner(img).unwrap()).unwrap());
-    let mut output_file: File = File::create(path).unwrap();
-    match output_image.write_to(&mut output_file, image::ImageOutputFormat::Jpeg(quality)) {
-        Ok(_) => {}
-        Err(_) => println!("Outputting image fails."),
-    }
+    Mutex::into_inner(Arc::into_inner(img).unwrap()).unwrap()
 }
 
 // pub fn multi_render(mut camera:  Camera, world: &HittableList)-> ImageBuffer<Rgb<u8>, Vec<u8>> {
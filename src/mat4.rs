@@ -0,0 +1,353 @@
+use crate::vec3::{Point3, Vec3};
+use std::ops::Mul;
+
+// 3x3矩阵，行主序，主要用于法线变换
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat3 {
+    pub m: [[f64; 3]; 3],
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mat3 {
+    pub fn new(m: [[f64; 3]; 3]) -> Self {
+        Self { m }
+    }
+
+    pub fn identity() -> Self {
+        Self::new([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = [[0.0; 3]; 3];
+        for (i, row) in t.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = self.m[j][i];
+            }
+        }
+        Self::new(t)
+    }
+
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    // 不可逆时返回None。行列式的绝对值不超过三行长度之积，按这个上界判断接近奇异，
+    // 与整体缩放无关，scale(1e-5)这样很小但良态的矩阵也能求逆
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        let bound: f64 = self.m.iter().map(|row| Vec3::new(row[0], row[1], row[2]).length()).product();
+        if det == 0.0 || det.abs() < 1e-12 * bound {
+            return None;
+        }
+        let m = &self.m;
+        let inv_det = 1.0 / det;
+        let mut r = [[0.0; 3]; 3];
+        r[0][0] = (m[1][1] * m[2][2] - m[1][2] * m[2][1]) * inv_det;
+        r[0][1] = (m[0][2] * m[2][1] - m[0][1] * m[2][2]) * inv_det;
+        r[0][2] = (m[0][1] * m[1][2] - m[0][2] * m[1][1]) * inv_det;
+        r[1][0] = (m[1][2] * m[2][0] - m[1][0] * m[2][2]) * inv_det;
+        r[1][1] = (m[0][0] * m[2][2] - m[0][2] * m[2][0]) * inv_det;
+        r[1][2] = (m[0][2] * m[1][0] - m[0][0] * m[1][2]) * inv_det;
+        r[2][0] = (m[1][0] * m[2][1] - m[1][1] * m[2][0]) * inv_det;
+        r[2][1] = (m[0][1] * m[2][0] - m[0][0] * m[2][1]) * inv_det;
+        r[2][2] = (m[0][0] * m[1][1] - m[0][1] * m[1][0]) * inv_det;
+        Some(Self::new(r))
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+// 4x4仿射变换矩阵，行主序，作用于列向量
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn identity() -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        Self::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scale(s: Vec3) -> Self {
+        Self::new([
+            [s.x, 0.0, 0.0, 0.0],
+            [0.0, s.y, 0.0, 0.0],
+            [0.0, 0.0, s.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // 角度制
+    pub fn rotate_x(angle: f64) -> Self {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, c, -s, 0.0],
+            [0.0, s, c, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotate_y(angle: f64) -> Self {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self::new([
+            [c, 0.0, s, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-s, 0.0, c, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotate_z(angle: f64) -> Self {
+        let (s, c) = angle.to_radians().sin_cos();
+        Self::new([
+            [c, -s, 0.0, 0.0],
+            [s, c, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // 绕任意轴旋转（Rodrigues公式）
+    pub fn rotate(axis: Vec3, angle: f64) -> Self {
+        let a = Vec3::unit_vector(axis);
+        let (s, c) = angle.to_radians().sin_cos();
+        let t = 1.0 - c;
+        Self::new([
+            [t * a.x * a.x + c, t * a.x * a.y - s * a.z, t * a.x * a.z + s * a.y, 0.0],
+            [t * a.x * a.y + s * a.z, t * a.y * a.y + c, t * a.y * a.z - s * a.x, 0.0],
+            [t * a.x * a.z - s * a.y, t * a.y * a.z + s * a.x, t * a.z * a.z + c, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // 左上角3x3线性部分
+    pub fn linear(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3::new([
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ])
+    }
+
+    // 仿射矩阵求逆：线性部分求逆，平移部分取 -A^-1 * t
+    pub fn inverse(&self) -> Option<Self> {
+        let a_inv = self.linear().inverse()?;
+        let t = a_inv * Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3]);
+        let a = &a_inv.m;
+        Some(Self::new([
+            [a[0][0], a[0][1], a[0][2], -t.x],
+            [a[1][0], a[1][1], a[1][2], -t.y],
+            [a[2][0], a[2][1], a[2][2], -t.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        Point3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.linear() * v
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let mut r = [[0.0; 4]; 4];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                *x = (0..4).map(|k| self.m[i][k] * other.m[k][j]).sum();
+            }
+        }
+        Self::new(r)
+    }
+}
+
+// 单位四元数，表示旋转；用于关键帧之间的球面插值
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quat {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quat {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    // 角度制
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let a = Vec3::unit_vector(axis);
+        let (s, c) = (angle.to_radians() / 2.0).sin_cos();
+        Self::new(c, s * a.x, s * a.y, s * a.z)
+    }
+
+    fn dot(&self, other: &Self) -> f64 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn normalize(&self) -> Self {
+        let len = self.dot(self).sqrt();
+        Self::new(self.w / len, self.x / len, self.y / len, self.z / len)
+    }
+
+    // 两个旋转之间的夹角（弧度）
+    pub fn angle_to(&self, other: &Self) -> f64 {
+        2.0 * self.dot(other).abs().min(1.0).acos()
+    }
+
+    // 球面线性插值，走较短的一侧
+    pub fn slerp(&self, other: &Self, t: f64) -> Self {
+        let mut cos_theta = self.dot(other);
+        let mut b = *other;
+        if cos_theta < 0.0 {
+            cos_theta = -cos_theta;
+            b = Self::new(-b.w, -b.x, -b.y, -b.z);
+        }
+        let (wa, wb) = if cos_theta > 0.9995 {
+            // 夹角很小时退化为线性插值
+            (1.0 - t, t)
+        } else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (((1.0 - t) * theta).sin() / sin_theta, (t * theta).sin() / sin_theta)
+        };
+        Self::new(
+            wa * self.w + wb * b.w,
+            wa * self.x + wb * b.x,
+            wa * self.y + wb * b.y,
+            wa * self.z + wb * b.z,
+        )
+        .normalize()
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        let Self { w, x, y, z } = self.normalize();
+        Mat4::new([
+            [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+            [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+            [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let m = Mat4::translate(Vec3::new(1.0, -2.0, 3.0))
+            * Mat4::rotate(Vec3::new(1.0, 1.0, 0.0), 37.0)
+            * Mat4::scale(Vec3::new(2.0, 0.5, 3.0));
+        let id = m * m.inverse().unwrap();
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((id.m[i][j] - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_singular() {
+        assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        assert!(Mat4::new([[1.0, 2.0, 3.0, 0.0], [2.0, 4.0, 6.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0; 4]]).inverse().is_none());
+
+        // 奇异与否与整体缩放无关
+        let tiny = Mat4::scale(Vec3::new(1e-5, 1e-5, 1e-5)).inverse().unwrap();
+        assert_near(tiny.transform_point(Point3::new(1e-5, 2e-5, 0.0)), Point3::new(1.0, 2.0, 0.0));
+        assert!(Mat4::scale(Vec3::new(1e5, 1e-9, 1e5)).inverse().is_some());
+        assert!(Mat4::scale(Vec3::new(1e5, 0.0, 1e5)).inverse().is_none());
+    }
+
+    #[test]
+    fn test_rotate_y_matches_axis() {
+        let p = Vec3::new(1.0, 2.0, 3.0);
+        assert_near(
+            Mat4::rotate_y(30.0).transform_point(p),
+            Mat4::rotate(Vec3::new(0.0, 1.0, 0.0), 30.0).transform_point(p),
+        );
+        assert_near(Mat4::rotate_z(90.0).transform_vector(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_quat_slerp() {
+        let axis = Vec3::new(1.0, 2.0, -1.0);
+        let p = Vec3::new(0.3, -1.0, 2.0);
+        let a = Quat::from_axis_angle(axis, 20.0);
+        let b = Quat::from_axis_angle(axis, 100.0);
+        assert_near(a.to_mat4().transform_point(p), Mat4::rotate(axis, 20.0).transform_point(p));
+        assert_near(a.slerp(&b, 0.25).to_mat4().transform_point(p), Mat4::rotate(axis, 40.0).transform_point(p));
+        assert!((a.angle_to(&b) - 80f64.to_radians()).abs() < 1e-9);
+    }
+}
@@ -44,6 +44,31 @@ impl Material for Lambertian {
     }
 }
 
+// 漫反射，反照率取自HitRecord中的逐点颜色，没有颜色时使用fallback
+pub struct VertexColor {
+    pub fallback: Arc<dyn Texture + Send + Sync>,
+}
+
+impl VertexColor {
+    pub fn new(fallback: Color) -> Self {
+        Self {
+            fallback: Arc::new(SolidColor::new(fallback)),
+        }
+    }
+}
+
+impl Material for VertexColor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+        *scattered = Ray::new_time(rec.p, scatter_direction, r_in.tm);
+        *attenuation = rec.color.unwrap_or_else(|| self.fallback.value(rec.u, rec.v, rec.p));
+        true
+    }
+}
+
 pub struct Metal {
     pub albedo: Color,
     pub fuzz: f64,
@@ -159,4 +184,111 @@ impl Material for Isotropic {
         *attenuation = self.tex.value(rec.u, rec.v, rec.p);
         true
     }
-}
\ No newline at end of file
+}
+// 简化的毛发散射模型：按菲涅尔概率随机选择R（表面反射）、TT（穿透）、TRT（内部反射一次）
+// 三个波瓣，光在纤维内部按sigma_a吸收。需要HitRecord中的切线，没有切线时退化为任取一个垂直于法线的方向
+pub struct Hair {
+    pub sigma_a: Color, //单位半径路径上的吸收系数
+    pub beta_m: f64,    //纵向粗糙度（弧度）
+    pub beta_n: f64,    //方位角粗糙度，[0,1]
+    pub eta: f64,
+    pub alpha: f64,     //表皮鳞片倾角（角度）
+}
+
+impl Hair {
+    pub fn new(sigma_a: Color, beta_m: f64, beta_n: f64) -> Self {
+        Self {
+            sigma_a,
+            beta_m,
+            beta_n,
+            eta: 1.55,
+            alpha: 2.0,
+        }
+    }
+
+    // 由真黑色素和褐黑色素浓度得到吸收系数（PBRT的拟合值）
+    pub fn from_melanin(eumelanin: f64, pheomelanin: f64, beta_m: f64, beta_n: f64) -> Self {
+        let sigma_a = eumelanin * Color::new(0.419, 0.697, 1.37) + pheomelanin * Color::new(0.187, 0.4, 1.05);
+        Self::new(sigma_a, beta_m, beta_n)
+    }
+
+    // 由期望的多次散射后颜色反推吸收系数
+    pub fn from_color(c: Color, beta_m: f64, beta_n: f64) -> Self {
+        let denom = 5.969 - 0.215 * beta_n + 2.532 * beta_n.powi(2) - 10.73 * beta_n.powi(3)
+            + 5.574 * beta_n.powi(4) + 0.245 * beta_n.powi(5);
+        let sigma = |x: f64| (x.max(1e-4).ln() / denom).powi(2);
+        Self::new(Color::new(sigma(c.x()), sigma(c.y()), sigma(c.z())), beta_m, beta_n)
+    }
+
+    // 改变方向与切线的夹角（纵向）
+    fn tilt(dir: Vec3, tangent: Vec3, angle: f64) -> Vec3 {
+        let sin_theta = Vec3::dot(dir, tangent).clamp(-1.0, 1.0);
+        let theta = sin_theta.asin() + angle;
+        let perp = dir - sin_theta * tangent;
+        if perp.near_zero() {
+            return dir;
+        }
+        theta.sin() * tangent + theta.cos() * Vec3::unit_vector(perp)
+    }
+
+    // 绕切线旋转（方位角）
+    fn rotate_about(dir: Vec3, axis: Vec3, phi: f64) -> Vec3 {
+        let (s, c) = phi.sin_cos();
+        c * dir + s * Vec3::cross(axis, dir) + (1.0 - c) * Vec3::dot(axis, dir) * axis
+    }
+
+    // 三角分布的随机数，范围[-1,1]
+    fn random_triangle() -> f64 {
+        random_double() + random_double() - 1.0
+    }
+}
+
+impl Material for Hair {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let d = Vec3::unit_vector(r_in.dir);
+        let (tangent, h) = if rec.tangent.near_zero() {
+            (crate::onb::Onb::new(rec.normal).u, 0.0)
+        } else {
+            // 曲线的v为横跨纤维的位置，映射到[-1,1]
+            (Vec3::unit_vector(rec.tangent), (2.0 * rec.v - 1.0).clamp(-1.0, 1.0))
+        };
+
+        let sin_theta = Vec3::dot(d, tangent).clamp(-1.0, 1.0);
+        let cos_theta = (1.0 - sin_theta * sin_theta).max(1e-4).sqrt();
+        let cos_gamma = Vec3::dot(-d, rec.normal).clamp(0.0, 1.0);
+        let f = Dielectric::reflectance(cos_gamma, self.eta);
+
+        // 纤维内部一次穿行的吸收
+        let sin_theta_t = sin_theta / self.eta;
+        let cos_theta_t = (1.0 - sin_theta_t * sin_theta_t).max(1e-4).sqrt();
+        let eta_p = (self.eta * self.eta - sin_theta * sin_theta).sqrt() / cos_theta;
+        let sin_gamma_t = (h / eta_p).clamp(-1.0, 1.0);
+        let cos_gamma_t = (1.0 - sin_gamma_t * sin_gamma_t).sqrt();
+        let path = 2.0 * cos_gamma_t / cos_theta_t;
+        let absorb = Color::new(
+            (-self.sigma_a.x() * path).exp(),
+            (-self.sigma_a.y() * path).exp(),
+            (-self.sigma_a.z() * path).exp(),
+        );
+
+        let alpha = self.alpha.to_radians();
+        let xi = random_double();
+        let (direction, weight) = if xi < f {
+            // R：表面反射，鳞片使高光向发梢方向偏移
+            (Self::tilt(Vec3::reflect(d, rec.normal), tangent, 2.0 * alpha), Color::ones())
+        } else if xi < f + (1.0 - f) * (1.0 - f) {
+            // TT：穿过纤维继续前进
+            (Self::tilt(d, tangent, -alpha), absorb)
+        } else {
+            // TRT：在背面反射一次后从入射一侧射出
+            (Self::tilt(Vec3::reflect(d, rec.normal), tangent, -4.0 * alpha), absorb * absorb)
+        };
+
+        let direction = Self::tilt(direction, tangent, self.beta_m * Self::random_triangle());
+        let direction = Self::rotate_about(direction, tangent, self.beta_n * std::f64::consts::PI * Self::random_triangle());
+
+        *scattered = Ray::new_time(rec.p, direction, r_in.tm);
+        *attenuation = weight;
+        true
+    }
+}
@@ -0,0 +1,281 @@
+use crate::aabb::Aabb;
+use crate::bvh::BvhSplit;
+use crate::csg::Solid;
+use crate::wide_bvh::DefaultBvh;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::packet::{RayPacket, MAX_PACKET};
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 索引三角网格；normals、uvs为空时分别使用面法线和重心坐标
+#[derive(Clone, Default)]
+pub struct TriangleMesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    pub fn new(positions: Vec<Point3>, indices: Vec<[usize; 3]>) -> Self {
+        Self {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices,
+        }
+    }
+
+    // 按面积加权平均相邻面的法线，得到平滑的顶点法线
+    pub fn compute_smooth_normals(&mut self) {
+        let mut normals = vec![Vec3::zero(); self.positions.len()];
+        for &[a, b, c] in &self.indices {
+            let n = Vec3::cross(self.positions[b] - self.positions[a], self.positions[c] - self.positions[a]);
+            normals[a] += n;
+            normals[b] += n;
+            normals[c] += n;
+        }
+        self.normals = normals
+            .into_iter()
+            .map(|n| if n.near_zero() { n } else { Vec3::unit_vector(n) })
+            .collect();
+    }
+
+    // 合并另一个网格，索引整体偏移。只有一方带法线或UV时，另一方补上零法线（求交时退回面法线）
+    // 和(0,0)的UV，保持与顶点一一对应
+    pub fn append(&mut self, other: &TriangleMesh) {
+        let (n, m) = (self.positions.len(), other.positions.len());
+        if !other.normals.is_empty() || !self.normals.is_empty() {
+            self.normals.resize(n, Vec3::zero());
+            self.normals.extend_from_slice(&other.normals);
+            self.normals.resize(n + m, Vec3::zero());
+        }
+        if !other.uvs.is_empty() || !self.uvs.is_empty() {
+            self.uvs.resize(n, (0.0, 0.0));
+            self.uvs.extend_from_slice(&other.uvs);
+            self.uvs.resize(n + m, (0.0, 0.0));
+        }
+        self.positions.extend_from_slice(&other.positions);
+        self.indices
+            .extend(other.indices.iter().map(|t| t.map(|i| i + n)));
+    }
+
+    // 每个三角形作为一个图元放进BVH
+    pub fn into_hittable(self, mat: Arc<dyn Material>) -> Arc<dyn Hittable> {
+        Arc::new(self.into_bvh(mat))
+    }
+
+    // 作为CSG的实体使用，网格须封闭且各面法线朝外（逆时针）
+    pub fn into_solid(self, mat: Arc<dyn Material>) -> Arc<dyn Solid> {
+        Arc::new(self.into_bvh(mat))
+    }
+
+    fn into_bvh(self, mat: Arc<dyn Material>) -> DefaultBvh {
+        let mesh = Arc::new(self);
+        let mut list = HittableList::default();
+        for index in 0..mesh.indices.len() {
+            list.add(Arc::new(MeshTriangle::new(Arc::clone(&mesh), index, Arc::clone(&mat))));
+        }
+        DefaultBvh::from_objects(list.objects, BvhSplit::default())
+    }
+}
+
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    index: usize,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl MeshTriangle {
+    pub fn new(mesh: Arc<TriangleMesh>, index: usize, mat: Arc<dyn Material>) -> Self {
+        let [a, b, c] = mesh.indices[index];
+        let (p0, p1, p2) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+        // 轴对齐的三角形包围盒厚度为0，需要填充
+        let bbox = Aabb::new_box(&Aabb::new_point(&p0, &p1), &Aabb::new_point(&p0, &p2)).pad();
+        Self { mesh, index, mat, bbox }
+    }
+
+    fn vertices(&self) -> (Point3, Point3, Point3) {
+        let [a, b, c] = self.mesh.indices[self.index];
+        (self.mesh.positions[a], self.mesh.positions[b], self.mesh.positions[c])
+    }
+
+    // Moller-Trumbore，返回交点的t和重心坐标b1、b2
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, f64, f64)> {
+        let (p0, p1, p2) = self.vertices();
+        let e1 = p1 - p0;
+        let e2 = p2 - p0;
+        let pvec = Vec3::cross(r.dir, e2);
+        let det = Vec3::dot(e1, pvec);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+        let tvec = r.orig - p0;
+        let b1 = Vec3::dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+        let qvec = Vec3::cross(tvec, e1);
+        let b2 = Vec3::dot(r.dir, qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+        let t = Vec3::dot(e2, qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+        Some((t, b1, b2))
+    }
+
+    // 由重心坐标插值法线和纹理坐标，填写交点信息
+    fn set_record<'a>(&'a self, r: &Ray, t: f64, b1: f64, b2: f64, rec: &mut HitRecord<'a>) {
+        let mesh = &self.mesh;
+        let [a, b, c] = mesh.indices[self.index];
+        let (p0, p1, p2) = self.vertices();
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let b0 = 1.0 - b1 - b2;
+
+        let normal = if mesh.normals.is_empty() {
+            Vec3::unit_vector(Vec3::cross(e1, e2))
+        } else {
+            let n = b0 * mesh.normals[a] + b1 * mesh.normals[b] + b2 * mesh.normals[c];
+            if n.near_zero() {
+                Vec3::unit_vector(Vec3::cross(e1, e2))
+            } else {
+                Vec3::unit_vector(n)
+            }
+        };
+        let (u, v) = if mesh.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let (ua, va) = mesh.uvs[a];
+            let (ub, vb) = mesh.uvs[b];
+            let (uc, vc) = mesh.uvs[c];
+            (b0 * ua + b1 * ub + b2 * uc, b0 * va + b1 * vb + b2 * vc)
+        };
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.set_face_normal(r, normal);
+        rec.u = u;
+        rec.v = v;
+        rec.set_material(&*self.mat);
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    // 重心坐标暂存在u、v中，surface再插值法线和纹理坐标
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some((t, b1, b2)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, t);
+        (rec.u, rec.v) = (b1, b2);
+        true
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        self.set_record(r, rec.t, rec.u, rec.v, rec);
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    // 逐通道做Moller-Trumbore，各项判定合成一个布尔值而不提前返回，循环体没有分支
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        let n = packet.len();
+        let (p0, p1, p2) = self.vertices();
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let mut ts = [0.0; MAX_PACKET];
+        let mut bs = [(0.0, 0.0); MAX_PACKET];
+        let mut valid = [false; MAX_PACKET];
+        for i in 0..n {
+            let d = Vec3::new(packet.dir[0][i], packet.dir[1][i], packet.dir[2][i]);
+            let o = Vec3::new(packet.orig[0][i], packet.orig[1][i], packet.orig[2][i]);
+            let pvec = Vec3::cross(d, e2);
+            let det = Vec3::dot(e1, pvec);
+            let inv_det = 1.0 / det;
+            let tvec = o - p0;
+            let b1 = Vec3::dot(tvec, pvec) * inv_det;
+            let qvec = Vec3::cross(tvec, e1);
+            let b2 = Vec3::dot(d, qvec) * inv_det;
+            let t = Vec3::dot(e2, qvec) * inv_det;
+            valid[i] = (det.abs() >= 1e-12)
+                & (0.0..=1.0).contains(&b1)
+                & (b2 >= 0.0)
+                & (b1 + b2 <= 1.0)
+                & (packet.t_min < t)
+                & (t < packet.t_max[i]);
+            ts[i] = t;
+            bs[i] = (b1, b2);
+        }
+        for i in 0..n {
+            if packet.active[i] && valid[i] {
+                let (b1, b2) = bs[i];
+                self.set_record(&packet.rays[i], ts[i], b1, b2, &mut packet.recs[i]);
+                packet.record_hit(i);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::util::INFINITY;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_append_aligns_attributes() {
+        // 带法线和UV的斜三角形，与不带属性的平放三角形合并
+        let mut tilted = TriangleMesh::new(
+            vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0)],
+            vec![[0, 1, 2]],
+        );
+        tilted.normals = vec![Vec3::new(0.0, 0.0, 1.0); 3];
+        tilted.uvs = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+        let flat = TriangleMesh::new(
+            vec![Point3::new(5.0, 0.0, 0.0), Point3::new(5.0, 0.0, 1.0), Point3::new(6.0, 0.0, 0.0)],
+            vec![[0, 1, 2]],
+        );
+
+        let mut mesh = flat.clone();
+        mesh.append(&tilted);
+        assert_eq!((mesh.normals.len(), mesh.uvs.len()), (6, 6));
+        assert_eq!(mesh.normals[3], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.uvs[4], (1.0, 0.0));
+        tilted.append(&flat);
+        assert_eq!((tilted.normals.len(), tilted.uvs.len()), (6, 6));
+
+        // 补上的顶点击中时用面法线，原有的顶点属性不受影响
+        let world = mesh.into_hittable(Arc::new(Lambertian::new(Color::ones())));
+        let t = Interval::new(0.001, INFINITY);
+        let mut rec = HitRecord::default();
+        assert!(world.hit(&Ray::new(Point3::new(5.2, 3.0, 0.2), Vec3::new(0.0, -1.0, 0.0)), &t, &mut rec));
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+        assert_eq!((rec.u, rec.v), (0.0, 0.0));
+        assert!(world.hit(&Ray::new(Point3::new(0.25, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0)), &t, &mut rec));
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((rec.u - 0.25).abs() < 1e-9 && (rec.v - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, Transform};
+use crate::interval::Interval;
+use crate::mat4::{Mat4, Quat};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 每段关键帧之间用于求包围盒的采样数
+const BOUND_SAMPLES: usize = 16;
+
+// 某一时刻的平移、旋转、缩放，按 平移 * 旋转 * 缩放 的顺序作用
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn translation(time: f64, translation: Vec3) -> Self {
+        Self::new(time, translation, Quat::identity(), Vec3::ones())
+    }
+
+    fn matrix(&self) -> Mat4 {
+        Mat4::translate(self.translation) * self.rotation.to_mat4() * Mat4::scale(self.scale)
+    }
+
+    // 平移和缩放线性插值，旋转球面插值
+    fn interpolate(&self, other: &Self, t: f64) -> Self {
+        Self::new(
+            (1.0 - t) * self.time + t * other.time,
+            (1.0 - t) * self.translation + t * other.translation,
+            self.rotation.slerp(&other.rotation, t),
+            (1.0 - t) * self.scale + t * other.scale,
+        )
+    }
+}
+
+// 随光线时间变化的变换，可作用于任意物体；关键帧范围之外保持首/末帧
+pub struct MotionTransform {
+    object: Arc<dyn Hittable>,
+    keys: Vec<Keyframe>,
+    bbox: Aabb,
+}
+
+impl MotionTransform {
+    pub fn new(object: Arc<dyn Hittable>, mut keys: Vec<Keyframe>) -> Self {
+        assert!(!keys.is_empty(), "MotionTransform needs at least one keyframe");
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let bbox = Self::motion_bounds(object.bounding_box(), &keys);
+        Self { object, keys, bbox }
+    }
+
+    fn keyframe_at(&self, time: f64) -> Keyframe {
+        let keys = &self.keys;
+        if time <= keys[0].time {
+            return keys[0];
+        }
+        if time >= keys[keys.len() - 1].time {
+            return keys[keys.len() - 1];
+        }
+        let i = keys.partition_point(|k| k.time <= time);
+        let (a, b) = (&keys[i - 1], &keys[i]);
+        a.interpolate(b, (time - a.time) / (b.time - a.time))
+    }
+
+    pub fn matrix_at(&self, time: f64) -> Mat4 {
+        self.keyframe_at(time).matrix()
+    }
+
+    // 光线时刻的逆矩阵和对象空间中的光线，矩阵不可逆时为None
+    fn to_object(&self, r: &Ray) -> Option<(Mat4, Ray)> {
+        let inv = self.matrix_at(r.tm).inverse()?;
+        let local_r = Ray::new_time(inv.transform_point(r.orig), inv.transform_vector(r.dir), r.tm);
+        Some((inv, local_r))
+    }
+
+    // 在每段关键帧之间采样变换后的包围盒，再按相邻采样间旋转的弦高向外扩张
+    fn motion_bounds(bbox: &Aabb, keys: &[Keyframe]) -> Aabb {
+        let mut result = Transform::transform_box(bbox, &keys[0].matrix());
+        let corner_radius = [bbox.x.min, bbox.x.max]
+            .iter()
+            .flat_map(|&x| [bbox.y.min, bbox.y.max].map(move |y| (x, y)))
+            .flat_map(|(x, y)| [bbox.z.min, bbox.z.max].map(move |z| Vec3::new(x, y, z).length()))
+            .fold(0.0, f64::max);
+
+        for pair in keys.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            let max_scale = [a.scale, b.scale]
+                .iter()
+                .map(|s| s.x().abs().max(s.y().abs()).max(s.z().abs()))
+                .fold(0.0, f64::max);
+            let step_angle = a.rotation.angle_to(&b.rotation) / BOUND_SAMPLES as f64;
+            let sagitta = corner_radius * max_scale * (1.0 - (step_angle / 2.0).cos());
+            let pad = Vec3::new(sagitta, sagitta, sagitta);
+
+            for i in 1..=BOUND_SAMPLES {
+                let key = a.interpolate(b, i as f64 / BOUND_SAMPLES as f64);
+                let sample = Transform::transform_box(bbox, &key.matrix());
+                let min = Point3::new(sample.x.min, sample.y.min, sample.z.min) - pad;
+                let max = Point3::new(sample.x.max, sample.y.max, sample.z.max) + pad;
+                result = Aabb::new_box(&result, &Aabb::new_point(&min, &max));
+            }
+        }
+        result
+    }
+}
+
+impl Hittable for MotionTransform {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some((inv, local_r)) = self.to_object(r) else {
+            return false;
+        };
+        if !self.object.hit_deferred(&local_r, ray_t, rec) {
+            return false;
+        }
+        rec.push_transform(&inv);
+        true
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.to_object(r).is_some_and(|(_, local_r)| self.object.occluded(&local_r, ray_t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::util;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_interpolated_translation() {
+        let sphere = Arc::new(Sphere::new(Point3::zero(), 1.0, Arc::new(Lambertian::new(Color::ones()))));
+        let moving = MotionTransform::new(
+            sphere,
+            vec![Keyframe::translation(0.0, Vec3::zero()), Keyframe::translation(1.0, Vec3::new(4.0, 0.0, 0.0))],
+        );
+        assert!(moving.bounding_box().x.min <= -1.0 && moving.bounding_box().x.max >= 5.0);
+
+        let mut rec = HitRecord::default();
+        let r = Ray::new_time(Point3::new(2.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.5);
+        assert!(moving.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 9.0).abs() < 1e-9);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+
+        let r = Ray::new_time(Point3::new(2.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(!moving.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_rotation_bounds_contain_samples() {
+        let sphere = Arc::new(Sphere::new(Point3::new(2.0, 0.0, 0.0), 0.5, Arc::new(Lambertian::new(Color::ones()))));
+        let spinning = MotionTransform::new(
+            sphere,
+            vec![
+                Keyframe::new(0.0, Vec3::zero(), Quat::identity(), Vec3::ones()),
+                Keyframe::new(1.0, Vec3::zero(), Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 180.0), Vec3::ones()),
+            ],
+        );
+        let bbox = spinning.bounding_box();
+        for i in 0..=100 {
+            let c = spinning.matrix_at(i as f64 / 100.0).transform_point(Point3::new(2.0, 0.0, 0.0));
+            assert!(bbox.x.min <= c.x() - 0.5 && c.x() + 0.5 <= bbox.x.max);
+            assert!(bbox.z.min <= c.z() - 0.5 && c.z() + 0.5 <= bbox.z.max);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::vec3::Vec3;
+
+// 正交基，w为给定方向
+#[derive(Clone, Copy, Default)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        let w = Vec3::unit_vector(n);
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::unit_vector(Vec3::cross(w, a));
+        let u = Vec3::cross(w, v);
+        Self { u, v, w }
+    }
+
+    // 局部坐标 -> 世界坐标
+    pub fn to_world(self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+
+    // 世界坐标 -> 局部坐标
+    pub fn to_local(self, a: Vec3) -> Vec3 {
+        Vec3::new(Vec3::dot(a, self.u), Vec3::dot(a, self.v), Vec3::dot(a, self.w))
+    }
+
+    // 沿各坐标轴，垂直于w的单位圆的半宽
+    pub fn disk_extent(&self, radius: f64) -> Vec3 {
+        Vec3::new(
+            radius * (1.0 - self.w.x() * self.w.x()).max(0.0).sqrt(),
+            radius * (1.0 - self.w.y() * self.w.y()).max(0.0).sqrt(),
+            radius * (1.0 - self.w.z() * self.w.z()).max(0.0).sqrt(),
+        )
+    }
+}
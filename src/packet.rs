@@ -0,0 +1,123 @@
+use crate::aabb::Aabb;
+use crate::hittable::HitRecord;
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+// 一个包最多容纳的光线数
+pub const MAX_PACKET: usize = 16;
+
+// 一组相干的光线（通常是相邻像素的主光线），一起穿过BVH并与图元求交。
+// 起点和方向按分量存成定长数组（SoA），图元对所有通道做同样的运算，便于编译器向量化；
+// active为false的通道不参与求交，命中后t_max收窄，hits和recs保存各条光线最近的交点
+pub struct RayPacket<'a> {
+    pub rays: Vec<Ray>,
+    pub orig: [[f64; MAX_PACKET]; 3],
+    pub dir: [[f64; MAX_PACKET]; 3],
+    pub t_min: f64,
+    pub t_max: [f64; MAX_PACKET],
+    pub active: [bool; MAX_PACKET],
+    pub hits: [bool; MAX_PACKET],
+    pub recs: Vec<HitRecord<'a>>,
+}
+
+impl RayPacket<'_> {
+    pub fn new(rays: Vec<Ray>, active: &[bool], ray_t: Interval) -> Self {
+        assert!(rays.len() <= MAX_PACKET && rays.len() == active.len());
+        let mut packet = Self {
+            orig: [[0.0; MAX_PACKET]; 3],
+            dir: [[0.0; MAX_PACKET]; 3],
+            t_min: ray_t.min,
+            t_max: [ray_t.max; MAX_PACKET],
+            active: [false; MAX_PACKET],
+            hits: [false; MAX_PACKET],
+            recs: vec![HitRecord::default(); rays.len()],
+            rays,
+        };
+        for (i, r) in packet.rays.iter().enumerate() {
+            for a in 0..3 {
+                packet.orig[a][i] = r.orig[a];
+                packet.dir[a][i] = r.dir[a];
+            }
+            packet.active[i] = active[i];
+        }
+        packet
+    }
+
+    pub fn len(&self) -> usize {
+        self.rays.len()
+    }
+
+    pub fn interval(&self, i: usize) -> Interval {
+        Interval::new(self.t_min, self.t_max[i])
+    }
+
+    // 第i条光线命中，recs[i]已填好
+    pub fn record_hit(&mut self, i: usize) {
+        self.hits[i] = true;
+        self.t_max[i] = self.recs[i].t;
+    }
+
+    // 从first开始找第一条与包围盒相交的活动光线。之前的光线已知错过父节点，不必再测；
+    // 之后的光线不再逐条测试，直接交给图元判断
+    pub fn first_hit(&self, bbox: &Aabb, first: usize) -> Option<usize> {
+        (first..self.len()).find(|&i| self.active[i] && bbox.hit(&self.rays[i], &mut self.interval(i)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::{BvhSplit, FlatBvh};
+    use crate::hittable::Hittable;
+    use crate::hittable_list::HittableList;
+    use crate::material::Lambertian;
+    use crate::mesh::TriangleMesh;
+    use crate::wide_bvh::WideBvh;
+    use crate::qard::Quad;
+    use crate::sphere::Sphere;
+    use crate::util::{random_double, random_double_range, INFINITY};
+    use crate::vec3::{Color, Point3, Vec3};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_packet_matches_single_rays() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::default();
+        for _ in 0..40 {
+            let center = Point3::new(random_double_range(-4.0, 4.0), random_double_range(-4.0, 4.0), random_double_range(-4.0, 4.0));
+            list.add(Arc::new(Sphere::new(center, random_double_range(0.2, 0.8), mat.clone())));
+        }
+        list.add(Arc::new(Quad::new(Point3::new(-5.0, -5.0, -5.0), Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 10.0, 0.0), mat.clone())));
+        let mesh = TriangleMesh::new(
+            vec![Point3::new(-3.0, 0.0, -3.0), Point3::new(3.0, 0.0, -3.0), Point3::new(0.0, 0.0, 3.0)],
+            vec![[0, 1, 2]],
+        );
+        list.add(mesh.into_hittable(mat));
+        let bvh = FlatBvh::from_objects(list.objects.clone(), BvhSplit::default());
+        let wide4 = WideBvh::<4>::from_objects(list.objects.clone(), BvhSplit::default());
+        let wide8 = WideBvh::<8>::from_objects(list.objects.clone(), BvhSplit::Sah { leaf_size: 2 });
+
+        for _ in 0..50 {
+            let orig = Point3::new(random_double_range(-1.0, 1.0), random_double_range(-1.0, 1.0), 10.0);
+            let rays: Vec<Ray> = (0..MAX_PACKET)
+                .map(|_| Ray::new(orig, Vec3::new(random_double_range(-0.5, 0.5), random_double_range(-0.5, 0.5), -1.0)))
+                .collect();
+            let active: Vec<bool> = (0..MAX_PACKET).map(|_| random_double() < 0.8).collect();
+            let ray_t = Interval::new(0.001, INFINITY);
+            for world in [&list as &dyn Hittable, &bvh, &wide4, &wide8] {
+                let mut packet = RayPacket::new(rays.clone(), &active, ray_t.clone());
+                world.hit_packet(&mut packet);
+                for (i, r) in rays.iter().enumerate() {
+                    let mut rec = HitRecord::default();
+                    let hit = active[i] && world.hit(r, &ray_t, &mut rec);
+                    assert_eq!(packet.hits[i], hit);
+                    if hit {
+                        assert!((packet.recs[i].t - rec.t).abs() < 1e-9);
+                        assert!((packet.recs[i].normal - rec.normal).length() < 1e-9);
+                    }
+                    assert_eq!(packet.active[i], active[i]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 无限平面，包围盒为整个空间
+pub struct Plane {
+    point: Point3,
+    onb: Onb,
+    uv_scale: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, mat: Arc<dyn Material>) -> Self {
+        Self::new_with_scale(point, normal, 1.0, mat)
+    }
+
+    // uv_scale为纹理重复一次对应的平面长度
+    pub fn new_with_scale(point: Point3, normal: Vec3, uv_scale: f64, mat: Arc<dyn Material>) -> Self {
+        Self {
+            point,
+            onb: Onb::new(normal),
+            uv_scale,
+            mat,
+            bbox: Aabb::UNIVERSE,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let normal = self.onb.w;
+        let denom = Vec3::dot(normal, r.dir);
+        //射线与平面平行
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+        let t = Vec3::dot(normal, self.point - r.orig) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+        rec.defer(self, t);
+
+        true
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        let normal = self.onb.w;
+        // 投影回平面，避免数值误差让空间纹理在平面上闪烁
+        let p = r.at(rec.t);
+        let local = self.onb.to_local(p - self.point);
+        rec.p = p - local.z() * normal;
+
+        rec.u = (local.x() / self.uv_scale).rem_euclid(1.0);
+        rec.v = (local.y() / self.uv_scale).rem_euclid(1.0);
+        rec.set_material(&*self.mat);
+        rec.set_face_normal(r, normal);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        let normal = self.onb.w;
+        let denom = Vec3::dot(normal, r.dir);
+        denom.abs() >= 1e-8 && ray_t.contains(Vec3::dot(normal, self.point - r.orig) / denom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_hit_and_uv() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let plane = Plane::new_with_scale(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 2.0, mat);
+        assert!(!plane.bounding_box().is_bounded());
+
+        let t = Interval::new(0.001, f64::INFINITY);
+        let mut rec = HitRecord::default();
+        assert!(plane.hit(&Ray::new(Point3::new(7.3, 4.0, -9.1), Vec3::new(0.0, -1.0, 0.0)), &t, &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-8);
+        assert!((rec.p - Point3::new(7.3, 1.0, -9.1)).length() < 1e-8);
+        assert!(rec.front_face);
+        assert!((0.0..1.0).contains(&rec.u) && (0.0..1.0).contains(&rec.v));
+
+        // 从下方击中背面；平行的光线不相交
+        assert!(plane.hit(&Ray::new(Point3::zero(), Vec3::new(0.0, 2.0, 0.0)), &t, &mut rec));
+        assert!((rec.t - 0.5).abs() < 1e-8);
+        assert!(!rec.front_face);
+        assert!(!plane.hit(&Ray::new(Point3::zero(), Vec3::new(1.0, 0.0, 0.0)), &t, &mut rec));
+    }
+}
@@ -0,0 +1,399 @@
+use crate::aabb::{Aabb, ROBUST_FACTOR};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Color, Point3, Vec3};
+use std::fs;
+use std::sync::Arc;
+
+// 每个点渲染成的形状
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PointShape {
+    Sphere,
+    // 有法线时按法线朝向，没有法线时始终朝向光线
+    Disk,
+}
+
+const LEAF_SIZE: usize = 4;
+
+// 紧凑的BVH节点：单精度包围盒，叶子时offset为第一个点，内部节点时为右子节点（左子节点紧随其后）
+#[derive(Clone, Copy, Default)]
+struct PointNode {
+    min: [f32; 3],
+    max: [f32; 3],
+    offset: u32,
+    count: u16,
+    axis: u8,
+}
+
+impl PointNode {
+    // 与Aabb::hit相同的稳健slab测试
+    fn hit(&self, r: &Ray, ray_t: &Interval) -> bool {
+        let mut t = ray_t.clone();
+        let inv_dir = r.inv_dir();
+        for a in 0..3 {
+            let (near, far) = if inv_dir[a] >= 0.0 { (self.min[a], self.max[a]) } else { (self.max[a], self.min[a]) };
+            let t0 = (near as f64 - r.orig[a]) * inv_dir[a];
+            let t1 = (far as f64 - r.orig[a]) * inv_dir[a] * ROBUST_FACTOR;
+            if t0 > t.min {
+                t.min = t0;
+            }
+            if t1 < t.max {
+                t.max = t1;
+            }
+            if t.min > t.max {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// 点云：位置和颜色用单精度/字节存储，所有点共享一个材质，颜色通过HitRecord::color传给材质
+pub struct PointCloud {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[u8; 3]>,
+    radius: f32,
+    shape: PointShape,
+    nodes: Vec<PointNode>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl PointCloud {
+    // normals可以为空；不为空时长度须与positions一致
+    pub fn new(
+        positions: Vec<[f32; 3]>,
+        normals: Vec<[f32; 3]>,
+        colors: Vec<[u8; 3]>,
+        radius: f64,
+        shape: PointShape,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        assert!(!positions.is_empty(), "point cloud must not be empty");
+        assert_eq!(positions.len(), colors.len());
+        assert!(normals.is_empty() || normals.len() == positions.len());
+
+        let mut cloud = Self {
+            positions,
+            normals,
+            colors,
+            radius: radius as f32,
+            shape,
+            nodes: Vec::new(),
+            mat,
+            bbox: Aabb::default(),
+        };
+        cloud.build();
+        let root = cloud.nodes[0];
+        cloud.bbox = Aabb::new_point(
+            &Point3::new(root.min[0] as f64, root.min[1] as f64, root.min[2] as f64),
+            &Point3::new(root.max[0] as f64, root.max[1] as f64, root.max[2] as f64),
+        );
+        cloud
+    }
+
+    // 文本格式，每行一个点："x y z"、"x y z r g b" 或 "x y z r g b nx ny nz"，颜色为0-255
+    pub fn parse_xyz(text: &str, radius: f64, shape: PointShape, mat: Arc<dyn Material>) -> Result<Self, String> {
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut colors = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.parse::<f32>().map_err(|e| format!("line {}: bad number \"{}\": {}", line_no + 1, s, e)))
+                .collect::<Result<Vec<f32>, String>>()?;
+            if ![3, 6, 9].contains(&values.len()) {
+                return Err(format!("line {}: expected 3, 6 or 9 values, got {}", line_no + 1, values.len()));
+            }
+            positions.push([values[0], values[1], values[2]]);
+            if values.len() >= 6 {
+                colors.push([values[3] as u8, values[4] as u8, values[5] as u8]);
+            } else {
+                colors.push([255, 255, 255]);
+            }
+            if values.len() == 9 {
+                normals.push([values[6], values[7], values[8]]);
+            }
+        }
+        if positions.is_empty() {
+            return Err("no points".to_string());
+        }
+        if !normals.is_empty() && normals.len() != positions.len() {
+            return Err("either all or none of the points must have normals".to_string());
+        }
+        Ok(Self::new(positions, normals, colors, radius, shape, mat))
+    }
+
+    pub fn load_xyz(filename: &str, radius: f64, shape: PointShape, mat: Arc<dyn Material>) -> Self {
+        let text = fs::read_to_string(filename)
+            .unwrap_or_else(|e| panic!("ERROR: Could not load point cloud \"{}\": {}", filename, e));
+        Self::parse_xyz(&text, radius, shape, mat)
+            .unwrap_or_else(|e| panic!("ERROR: Could not parse point cloud \"{}\": {}", filename, e))
+    }
+
+    fn position(&self, i: usize) -> Point3 {
+        let p = self.positions[i];
+        Point3::new(p[0] as f64, p[1] as f64, p[2] as f64)
+    }
+
+    // 按中位数划分建树，最后按叶子顺序重排点数据，使每个叶子的点连续存放
+    fn build(&mut self) {
+        let mut order: Vec<u32> = (0..self.positions.len() as u32).collect();
+        let mut nodes = Vec::with_capacity(2 * self.positions.len() / LEAF_SIZE + 1);
+        self.build_node(&mut order, 0, &mut nodes);
+        self.nodes = nodes;
+
+        let positions = order.iter().map(|&i| self.positions[i as usize]).collect();
+        let colors = order.iter().map(|&i| self.colors[i as usize]).collect();
+        if !self.normals.is_empty() {
+            self.normals = order.iter().map(|&i| self.normals[i as usize]).collect();
+        }
+        self.positions = positions;
+        self.colors = colors;
+    }
+
+    fn build_node(&self, order: &mut [u32], start: usize, nodes: &mut Vec<PointNode>) -> usize {
+        // 半径略微放大，抵消单精度舍入
+        let r = self.radius * 1.001;
+        let mut node = PointNode {
+            min: [f32::INFINITY; 3],
+            max: [f32::NEG_INFINITY; 3],
+            ..Default::default()
+        };
+        for &i in order.iter() {
+            let p = self.positions[i as usize];
+            for (a, &x) in p.iter().enumerate() {
+                node.min[a] = node.min[a].min(x - r);
+                node.max[a] = node.max[a].max(x + r);
+            }
+        }
+
+        let index = nodes.len();
+        nodes.push(node);
+        if order.len() <= LEAF_SIZE {
+            nodes[index].offset = start as u32;
+            nodes[index].count = order.len() as u16;
+            return index;
+        }
+
+        let extent = (0..3).map(|a| node.max[a] - node.min[a]).collect::<Vec<f32>>();
+        let axis = (0..3).max_by(|&a, &b| extent[a].total_cmp(&extent[b])).unwrap();
+        let mid = order.len() / 2;
+        order.select_nth_unstable_by(mid, |&a, &b| {
+            self.positions[a as usize][axis].total_cmp(&self.positions[b as usize][axis])
+        });
+        let (left, right) = order.split_at_mut(mid);
+        self.build_node(left, start, nodes);
+        let right_index = self.build_node(right, start + mid, nodes);
+        nodes[index].offset = right_index as u32;
+        nodes[index].axis = axis as u8;
+        index
+    }
+
+    // 圆片的法线，没有逐点法线时朝向光线
+    fn disk_normal(&self, i: usize, r: &Ray) -> Vec3 {
+        if self.normals.is_empty() {
+            -Vec3::unit_vector(r.dir)
+        } else {
+            let n = self.normals[i];
+            Vec3::unit_vector(Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64))
+        }
+    }
+
+    // 第i个点的交点t
+    fn intersect_point(&self, i: usize, r: &Ray, ray_t: &Interval) -> Option<f64> {
+        let center = self.position(i);
+        let radius = self.radius as f64;
+        let oc = center - r.orig;
+
+        Some(match self.shape {
+            PointShape::Sphere => {
+                let a = r.dir.squared_length();
+                let h = Vec3::dot(r.dir, oc);
+                let c = oc.squared_length() - radius * radius;
+                let discriminant = h * h - a * c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let sqrtd = discriminant.sqrt();
+                let mut root = (h - sqrtd) / a;
+                if !ray_t.surrounds(root) {
+                    root = (h + sqrtd) / a;
+                    if !ray_t.surrounds(root) {
+                        return None;
+                    }
+                }
+                root
+            }
+            PointShape::Disk => {
+                let n = self.disk_normal(i, r);
+                let denom = Vec3::dot(n, r.dir);
+                if denom.abs() < 1e-12 {
+                    return None;
+                }
+                let t = Vec3::dot(oc, n) / denom;
+                if !ray_t.surrounds(t) || (r.at(t) - center).squared_length() > radius * radius {
+                    return None;
+                }
+                t
+            }
+        })
+    }
+
+    // 命中的点的下标存入HitRecord::part
+    fn hit_point<'a>(&'a self, i: usize, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some(t) = self.intersect_point(i, r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, t);
+        rec.part = i;
+        true
+    }
+}
+
+impl Hittable for PointCloud {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let mut closest = ray_t.clone();
+        let mut hit_anything = false;
+
+        // 显式栈遍历，先访问光线方向上较近的子节点
+        let mut stack = [0usize; 64];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let node = &self.nodes[stack[top]];
+            if !node.hit(r, &closest) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for i in start..start + node.count as usize {
+                    if self.hit_point(i, r, &closest, rec) {
+                        hit_anything = true;
+                        closest.max = rec.t;
+                    }
+                }
+            } else {
+                let left = stack[top] + 1;
+                let right = node.offset as usize;
+                let (near, far) = if r.dir_is_neg(node.axis as usize) { (right, left) } else { (left, right) };
+                stack[top] = far;
+                stack[top + 1] = near;
+                top += 2;
+            }
+        }
+        hit_anything
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        let i = rec.part;
+        rec.p = r.at(rec.t);
+        let outward_normal = match self.shape {
+            PointShape::Sphere => (rec.p - self.position(i)) / self.radius as f64,
+            PointShape::Disk => self.disk_normal(i, r),
+        };
+        let c = self.colors[i];
+        rec.set_face_normal(r, outward_normal);
+        rec.u = 0.0;
+        rec.v = 0.0;
+        rec.set_material(&*self.mat);
+        rec.color = Some(Color::new(c[0] as f64, c[1] as f64, c[2] as f64) / 255.0);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        let mut stack = [0usize; 64];
+        let mut top = 1;
+        while top > 0 {
+            top -= 1;
+            let index = stack[top];
+            let node = &self.nodes[index];
+            if !node.hit(r, ray_t) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                if (start..start + node.count as usize).any(|i| self.intersect_point(i, r, ray_t).is_some()) {
+                    return true;
+                }
+            } else {
+                stack[top] = node.offset as usize;
+                stack[top + 1] = index + 1;
+                top += 2;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::util;
+
+    #[test]
+    fn test_hit_nearest_point() {
+        // 沿z排成一列的点，光线从+z射入应命中最近的一个
+        let text: String = (0..100).map(|i| format!("0 0 {} {} 0 0\n", -i, i)).collect();
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cloud = PointCloud::parse_xyz(&text, 0.1, PointShape::Sphere, mat).unwrap();
+        assert_eq!(cloud.positions.len(), 100);
+
+        let mut rec = HitRecord::default();
+        let r = Ray::new(Point3::new(0.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cloud.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 9.9).abs() < 1e-6);
+        assert_eq!(rec.color, Some(Color::new(0.0, 0.0, 0.0)));
+
+        // 从点列中间出发，命中下一个点
+        let r = Ray::new(Point3::new(0.0, 0.0, -41.5), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cloud.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.color.unwrap().x() - 42.0 / 255.0).abs() < 1e-9);
+
+        let r = Ray::new(Point3::new(0.5, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(!cloud.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_closer_hit_clears_color() {
+        use crate::bvh::FlatBvh;
+        use crate::hittable_list::HittableList;
+        use crate::sphere::Sphere;
+
+        // 先命中较远的点，再命中较近的球，VertexColor应退回到球的fallback而不是点的颜色
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cloud = PointCloud::parse_xyz("0 0 0 255 0 0\n", 0.1, PointShape::Sphere, mat.clone()).unwrap();
+        let mut list = HittableList::default();
+        list.add(Arc::new(cloud));
+        list.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, 2.0), 0.5, mat)));
+        let flat = FlatBvh::new(&list);
+
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        for world in [&list as &dyn Hittable, &flat] {
+            let mut rec = HitRecord::default();
+            assert!(world.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+            assert!((rec.t - 2.5).abs() < 1e-8);
+            assert_eq!(rec.color, None);
+        }
+    }
+}
@@ -6,8 +6,102 @@ use crate::ray::Ray;
 use crate::interval::Interval;
 use crate::hittable::{HitRecord,Hittable};
 use crate::hittable_list::HittableList;
+use crate::onb::Onb;
+use crate::packet::{RayPacket, MAX_PACKET};
+use crate::util;
 
-pub struct Quad {
+// 平面图元的内部判定和纹理坐标：(a, b) 为交点在 q + a*u + b*v 坐标下的平面坐标
+pub trait PlanarShape: Send + Sync {
+    fn is_interior(&self, a: f64, b: f64) -> bool;
+    fn uv(&self, a: f64, b: f64) -> (f64, f64);
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> Aabb;
+}
+
+// 平行四边形，q为一角
+pub struct Parallelogram;
+
+impl PlanarShape for Parallelogram {
+    fn is_interior(&self, a: f64, b: f64) -> bool {
+        (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b)
+    }
+
+    fn uv(&self, a: f64, b: f64) -> (f64, f64) {
+        (a, b)
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> Aabb {
+        Aabb::new_box(
+            &Aabb::new_point(&q, &(q + u + v)),
+            &Aabb::new_point(&(q + u), &(q + v)),
+        ).pad()
+    }
+}
+
+// 三角形，顶点为q, q+u, q+v
+pub struct TriangleShape;
+
+impl PlanarShape for TriangleShape {
+    fn is_interior(&self, a: f64, b: f64) -> bool {
+        a >= 0.0 && b >= 0.0 && a + b <= 1.0
+    }
+
+    fn uv(&self, a: f64, b: f64) -> (f64, f64) {
+        (a, b)
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> Aabb {
+        Aabb::new_box(
+            &Aabb::new_point(&q, &(q + u)),
+            &Aabb::new_point(&q, &(q + v)),
+        ).pad()
+    }
+}
+
+// 椭圆，q为中心，u、v为两个半轴
+pub struct EllipseShape;
+
+impl PlanarShape for EllipseShape {
+    fn is_interior(&self, a: f64, b: f64) -> bool {
+        a * a + b * b <= 1.0
+    }
+
+    fn uv(&self, a: f64, b: f64) -> (f64, f64) {
+        (0.5 * (a + 1.0), 0.5 * (b + 1.0))
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> Aabb {
+        let e = Vec3::new(
+            (u.x() * u.x() + v.x() * v.x()).sqrt(),
+            (u.y() * u.y() + v.y() * v.y()).sqrt(),
+            (u.z() * u.z() + v.z() * v.z()).sqrt(),
+        );
+        Aabb::new_point(&(q - e), &(q + e)).pad()
+    }
+}
+
+// 椭圆环，inner为内径与外径之比
+pub struct AnnulusShape {
+    inner: f64,
+}
+
+impl PlanarShape for AnnulusShape {
+    fn is_interior(&self, a: f64, b: f64) -> bool {
+        let dist2 = a * a + b * b;
+        dist2 <= 1.0 && dist2 >= self.inner * self.inner
+    }
+
+    // u为角度，v为从内径到外径的径向位置
+    fn uv(&self, a: f64, b: f64) -> (f64, f64) {
+        let dist = (a * a + b * b).sqrt();
+        ((b.atan2(a) + util::PI) / (2.0 * util::PI), (dist - self.inner) / (1.0 - self.inner))
+    }
+
+    fn bounding_box(&self, q: Point3, u: Vec3, v: Vec3) -> Aabb {
+        EllipseShape.bounding_box(q, u, v)
+    }
+}
+
+pub struct Planar<S: PlanarShape> {
     q: Point3,
     u: Vec3,
     v: Vec3,
@@ -16,10 +110,16 @@ pub struct Quad {
     mat: Arc<dyn Material>,
     bbox: Aabb,
     d: f64,
+    shape: S,
 }
 
-impl Quad {
-    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+pub type Quad = Planar<Parallelogram>;
+pub type Triangle = Planar<TriangleShape>;
+pub type Ellipse = Planar<EllipseShape>;
+pub type Annulus = Planar<AnnulusShape>;
+
+impl<S: PlanarShape> Planar<S> {
+    pub fn new_shape(q: Point3, u: Vec3, v: Vec3, shape: S, mat: Arc<dyn Material>) -> Self {
         let n = Vec3::cross(u, v);
         let normal = Vec3::unit_vector(n);
         Self {
@@ -28,66 +128,166 @@ impl Quad {
             v,
             w: n / Vec3::dot(n, n),
             mat,
-            bbox: Aabb::new_point(
-                &q, &(q + u + v)
-            ),
+            bbox: shape.bounding_box(q, u, v),
             normal,
             d: Vec3::dot(normal, q),
+            shape,
         }
     }
 
-    pub fn is_interior(&self, a: f64, b: f64, rec: &mut HitRecord) -> bool {
-        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
-            return false;
-        }
-
-        rec.u = a;
-        rec.v = b;
-
-        true
+    // 光线所在平面上t处交点的平面坐标
+    fn plane_coords(&self, r: &Ray, t: f64) -> (f64, f64) {
+        let planar_hitpt_vector = r.at(t) - self.q;
+        let alpha = Vec3::dot(self.w, Vec3::cross(planar_hitpt_vector, self.v));
+        let beta = Vec3::dot(self.w, Vec3::cross(self.u, planar_hitpt_vector));
+        (alpha, beta)
     }
-        
-}
 
-impl Hittable for Quad {
-    fn hit(&self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord) -> bool {
+    // 光线与平面的交点参数，平行或不在区间内时为None
+    fn plane_t(&self, r: &Ray, ray_t: &Interval) -> Option<f64> {
         let denom = Vec3::dot(self.normal, r.dir);
         //射线与平面平行
         if denom.abs() < 1e-8 {
-            return false;
+            return None;
         }
         //相交点射线区间之外
         let t = (self.d - Vec3::dot(self.normal, r.orig)) / denom;
-        if !ray_t.contains(t) {
+        ray_t.contains(t).then_some(t)
+    }
+
+    // 已知光线在t处与平面相交，判断交点是否在形状内部并填写交点信息
+    fn hit_at<'a>(&'a self, r: &Ray, t: f64, rec: &mut HitRecord<'a>) -> bool {
+        let (alpha, beta) = self.plane_coords(r, t);
+        if !self.shape.is_interior(alpha, beta) {
             return false;
         }
+        self.set_record(r, t, alpha, beta, rec);
+        true
+    }
 
-        let intersection = r.at(t);
+    fn set_record<'a>(&'a self, r: &Ray, t: f64, alpha: f64, beta: f64, rec: &mut HitRecord<'a>) {
+        rec.t = t;
+        rec.p = r.at(t);
+        (rec.u, rec.v) = self.shape.uv(alpha, beta);
+        rec.set_material(&*self.mat);
+        rec.set_face_normal(r, self.normal);
+    }
+}
 
-        let intersection = r.at(t);
-       let planar_hitpt_vector = intersection - self.q;
-       let alpha = Vec3::dot(self.w, Vec3::cross(planar_hitpt_vector, self.v));
-       let beta = Vec3::dot(self.w, Vec3::cross(self.u, planar_hitpt_vector));
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        Self::new_shape(q, u, v, Parallelogram, mat)
+    }
+}
 
-       if !self.is_interior(alpha, beta, rec) {
-           return false;
-       }
+impl Triangle {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        Self::new_shape(q, u, v, TriangleShape, mat)
+    }
 
-        rec.t = t;
-        rec.p = intersection;
-        rec.mat = Some(Arc::clone(&self.mat));
-        rec.set_face_normal(r, self.normal);
+    pub fn from_points(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        Self::new(a, b - a, c - a, mat)
+    }
+}
+
+// 圆盘所在平面的两个正交半轴
+fn disk_axes(normal: Vec3, radius: f64) -> (Vec3, Vec3) {
+    let onb = Onb::new(normal);
+    // cross(v, u) == w，保证平面法线与normal同向
+    (onb.v * radius, onb.u * radius)
+}
+
+impl Ellipse {
+    pub fn new(center: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        Self::new_shape(center, u, v, EllipseShape, mat)
+    }
+
+    pub fn new_disk(center: Point3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let (u, v) = disk_axes(normal, radius);
+        Self::new(center, u, v, mat)
+    }
+}
+
+impl Annulus {
+    // inner为内径与外径之比，须小于1，否则圆环为空且径向的v无法定义
+    pub fn new(center: Point3, u: Vec3, v: Vec3, inner: f64, mat: Arc<dyn Material>) -> Self {
+        assert!((0.0..1.0).contains(&inner), "annulus inner radius must be in [0, 1) of the outer radius");
+        Self::new_shape(center, u, v, AnnulusShape { inner }, mat)
+    }
+
+    pub fn new_ring(center: Point3, normal: Vec3, inner_radius: f64, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let (u, v) = disk_axes(normal, radius);
+        Self::new(center, u, v, inner_radius / radius, mat)
+    }
+}
 
+impl<S: PlanarShape> Hittable for Planar<S> {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    // 平面坐标暂存在u、v中，surface再换算成纹理坐标
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some(t) = self.plane_t(r, ray_t) else {
+            return false;
+        };
+        let (alpha, beta) = self.plane_coords(r, t);
+        if !self.shape.is_interior(alpha, beta) {
+            return false;
+        }
+        rec.defer(self, t);
+        (rec.u, rec.v) = (alpha, beta);
         true
     }
 
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        self.set_record(r, rec.t, rec.u, rec.v, rec);
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.plane_t(r, ray_t).is_some_and(|t| {
+            let (alpha, beta) = self.plane_coords(r, t);
+            self.shape.is_interior(alpha, beta)
+        })
+    }
+
+    // 逐通道求光线与平面的交点参数，落在区间内的再做形状内部测试
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        let n = packet.len();
+        let (nx, ny, nz) = (self.normal.x(), self.normal.y(), self.normal.z());
+        let mut ts = [f64::NAN; MAX_PACKET];
+        for (i, t) in ts.iter_mut().enumerate().take(n) {
+            let denom = nx * packet.dir[0][i] + ny * packet.dir[1][i] + nz * packet.dir[2][i];
+            let dist = self.d - (nx * packet.orig[0][i] + ny * packet.orig[1][i] + nz * packet.orig[2][i]);
+            let candidate = dist / denom;
+            *t = if denom.abs() >= 1e-8 && packet.t_min <= candidate && candidate <= packet.t_max[i] {
+                candidate
+            } else {
+                f64::NAN
+            };
+        }
+        for (i, &t) in ts.iter().enumerate().take(n) {
+            if packet.active[i] && !t.is_nan() && self.hit_at(&packet.rays[i], t, &mut packet.recs[i]) {
+                packet.record_hit(i);
+            }
+        }
+    }
+
 
     fn bounding_box(&self) -> &Aabb {
         &self.bbox
     }
 }
 pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> Arc<dyn Hittable> {
-    //两个对角顶点a和b的盒子
+    Arc::new(box_sides(a, b, mat))
+}
+
+pub fn box_sides(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    //两个对角顶点a和b的盒子，六个面的法线朝外
 
     let mut sides = HittableList::default();
 
@@ -126,5 +326,96 @@ pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material>) -> Arc<dyn Hittabl
         Quad::new(Point3::new(min.x(), min.y(), min.z()), dx, dz, Arc::clone(&mat))
     ));
 
+    sides
+}
+
+// 由三点构造三角形，必要时交换后两个顶点，使法线背离实体内部的inside点
+fn outward_triangle(a: Point3, b: Point3, c: Point3, inside: Point3, mat: Arc<dyn Material>) -> Triangle {
+    if Vec3::dot(Vec3::cross(b - a, c - a), inside - a) > 0.0 {
+        Triangle::from_points(a, c, b, mat)
+    } else {
+        Triangle::from_points(a, b, c, mat)
+    }
+}
+
+pub fn make_tetrahedron(a: Point3, b: Point3, c: Point3, d: Point3, mat: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    //四个顶点的四面体，顶点顺序任意，各面法线都朝外
+    let mut sides = HittableList::default();
+
+    let centroid = (a + b + c + d) / 4.0;
+    for (p0, p1, p2) in [(a, b, c), (a, b, d), (a, c, d), (b, c, d)] {
+        sides.add(Arc::new(outward_triangle(p0, p1, p2, centroid, Arc::clone(&mat))));
+    }
+
+    Arc::new(sides)
+}
+
+pub fn make_pyramid(q: Point3, u: Vec3, v: Vec3, apex: Point3, mat: Arc<dyn Material>) -> Arc<dyn Hittable> {
+    //底面为平行四边形q,u,v的棱锥，顶点可在底面任意一侧，各面法线都朝外
+    let mut sides = HittableList::default();
+
+    let corners = [q, q + u, q + u + v, q + v];
+    // 底面中心与顶点的中点一定在棱锥内部
+    let inside = ((q + (u + v) / 2.0) + apex) / 2.0;
+    if Vec3::dot(Vec3::cross(u, v), apex - q) > 0.0 {
+        sides.add(Arc::new(Quad::new(q, v, u, Arc::clone(&mat))));
+    } else {
+        sides.add(Arc::new(Quad::new(q, u, v, Arc::clone(&mat))));
+    }
+    for i in 0..4 {
+        sides.add(Arc::new(outward_triangle(corners[i], corners[(i + 1) % 4], apex, inside, Arc::clone(&mat))));
+    }
+
     Arc::new(sides)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::util;
+    use crate::vec3::Color;
+
+    // 从内部点射向各面中心，命中的都应是背面，即各面法线背离内部
+    fn assert_outward(solid: &Arc<dyn Hittable>, inside: Point3, face_centers: &[Point3]) {
+        for &center in face_centers {
+            let r = Ray::new(inside, center - inside);
+            let mut rec = HitRecord::default();
+            assert!(solid.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+            assert!((rec.t - 1.0).abs() < 1e-9);
+            assert!(!rec.front_face, "face at {:?} points inward", center);
+        }
+    }
+
+    #[test]
+    fn test_face_normals_point_outward() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+
+        let (a, b, c, d) = (Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0), Point3::new(0.0, 0.0, 1.0));
+        let centroid = (a + b + c + d) / 4.0;
+        let faces = [(a + b + c) / 3.0, (a + b + d) / 3.0, (a + c + d) / 3.0, (b + c + d) / 3.0];
+        // 顶点顺序不影响朝向
+        assert_outward(&make_tetrahedron(a, b, c, d, Arc::clone(&mat)), centroid, &faces);
+        assert_outward(&make_tetrahedron(b, a, c, d, Arc::clone(&mat)), centroid, &faces);
+
+        // 顶点分别在底面法线的两侧
+        let (q, u, v) = (Point3::new(-1.0, 0.0, -1.0), Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 2.0));
+        for apex in [Point3::new(0.0, 1.5, 0.0), Point3::new(0.0, -1.5, 0.0)] {
+            let corners = [q, q + u, q + u + v, q + v];
+            let mut faces = vec![q + (u + v) / 2.0];
+            for i in 0..4 {
+                faces.push((corners[i] + corners[(i + 1) % 4] + apex) / 3.0);
+            }
+            let inside = ((q + (u + v) / 2.0) + apex) / 2.0;
+            assert_outward(&make_pyramid(q, u, v, apex, Arc::clone(&mat)), inside, &faces);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "annulus inner radius")]
+    fn test_annulus_rejects_empty_ring() {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::ones()));
+        Annulus::new_ring(Point3::zero(), Vec3::new(0.0, 1.0, 0.0), 1.0, 1.0, mat);
+    }
+
+}
@@ -1,25 +1,41 @@
 //acknowledgement : jj
 use crate::vec3::{Point3, Vec3};
+// inv_dir和sign在构造时由dir算出，供包围盒求交使用；修改dir后需重新构造光线
 #[derive(Default,Clone,Copy)]
 pub struct Ray {
     pub orig:Point3,
     pub dir:Vec3,
     pub tm:f64,
+    inv_dir: Vec3,
+    sign: u8,
 }
 impl Ray{
     pub fn new(orig:Point3, dir: Vec3,) -> Self {
-        Self { orig, dir, tm:0.0 }
+        Self::new_time(orig, dir, 0.0)
     }
 
     pub fn new_time(orig: Point3, dir: Vec3, tm: f64) -> Self {
+        // 分量为±0时倒数为±无穷，符号与分量的符号位一致
+        let inv_dir = Vec3::new(1.0 / dir.x(), 1.0 / dir.y(), 1.0 / dir.z());
         Self {
             orig,
             dir,
             tm,
+            inv_dir,
+            sign: (0..3).fold(0, |bits, a| bits | ((dir[a].is_sign_negative() as u8) << a)),
         }
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
         self.orig + self.dir * t
     }
-}
\ No newline at end of file
+
+    pub fn inv_dir(&self) -> &Vec3 {
+        &self.inv_dir
+    }
+
+    // axis轴上的方向分量为负（含-0）
+    pub fn dir_is_neg(&self, axis: usize) -> bool {
+        self.sign >> axis & 1 != 0
+    }
+}
@@ -0,0 +1,460 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+// 有符号距离函数：外部为正，内部为负
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: Point3) -> f64;
+}
+
+// 任意闭包都可以直接当作距离函数
+impl<F: Fn(Point3) -> f64 + Send + Sync> Sdf for F {
+    fn distance(&self, p: Point3) -> f64 {
+        self(p)
+    }
+}
+
+pub struct SdfSphere {
+    pub center: Point3,
+    pub radius: f64,
+}
+
+impl SdfSphere {
+    pub fn new(center: Point3, radius: f64) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Point3) -> f64 {
+        (p - self.center).length() - self.radius
+    }
+}
+
+// 轴对齐长方体，half为半边长
+pub struct SdfBox {
+    pub center: Point3,
+    pub half: Vec3,
+    pub rounding: f64,
+}
+
+impl SdfBox {
+    pub fn new(center: Point3, half: Vec3) -> Self {
+        Self::new_rounded(center, half, 0.0)
+    }
+
+    pub fn new_rounded(center: Point3, half: Vec3, rounding: f64) -> Self {
+        Self { center, half, rounding }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let q = Vec3::new(p.x().abs(), p.y().abs(), p.z().abs()) - self.half + self.rounding;
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside - self.rounding
+    }
+}
+
+// 对称轴为y
+pub struct SdfTorus {
+    pub center: Point3,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl SdfTorus {
+    pub fn new(center: Point3, major_radius: f64, minor_radius: f64) -> Self {
+        Self { center, major_radius, minor_radius }
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - self.major_radius;
+        (ring * ring + p.y() * p.y()).sqrt() - self.minor_radius
+    }
+}
+
+// 对称轴为y的封口圆柱
+pub struct SdfCylinder {
+    pub center: Point3,
+    pub radius: f64,
+    pub half_height: f64,
+}
+
+impl SdfCylinder {
+    pub fn new(center: Point3, radius: f64, half_height: f64) -> Self {
+        Self { center, radius, half_height }
+    }
+}
+
+impl Sdf for SdfCylinder {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let dx = (p.x() * p.x() + p.z() * p.z()).sqrt() - self.radius;
+        let dy = p.y().abs() - self.half_height;
+        dx.max(dy).min(0.0) + (dx.max(0.0) * dx.max(0.0) + dy.max(0.0) * dy.max(0.0)).sqrt()
+    }
+}
+
+pub struct SdfCapsule {
+    pub a: Point3,
+    pub b: Point3,
+    pub radius: f64,
+}
+
+impl SdfCapsule {
+    pub fn new(a: Point3, b: Point3, radius: f64) -> Self {
+        Self { a, b, radius }
+    }
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, p: Point3) -> f64 {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let h = (Vec3::dot(pa, ba) / Vec3::dot(ba, ba)).clamp(0.0, 1.0);
+        (pa - ba * h).length() - self.radius
+    }
+}
+
+pub struct SdfUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl SdfUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for SdfUnion {
+    fn distance(&self, p: Point3) -> f64 {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+}
+
+pub struct SdfIntersect {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl SdfIntersect {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for SdfIntersect {
+    fn distance(&self, p: Point3) -> f64 {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+}
+
+// a减去b
+pub struct SdfSubtract {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl SdfSubtract {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for SdfSubtract {
+    fn distance(&self, p: Point3) -> f64 {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+}
+
+// 多项式平滑并集，k为过渡区宽度
+pub struct SdfSmoothUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SdfSmoothUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SdfSmoothUnion {
+    fn distance(&self, p: Point3) -> f64 {
+        let (d1, d2) = (self.a.distance(p), self.b.distance(p));
+        let h = (0.5 + 0.5 * (d2 - d1) / self.k).clamp(0.0, 1.0);
+        d2 + (d1 - d2) * h - self.k * h * (1.0 - h)
+    }
+}
+
+pub struct SdfSmoothSubtract {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SdfSmoothSubtract {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SdfSmoothSubtract {
+    fn distance(&self, p: Point3) -> f64 {
+        let (d1, d2) = (self.a.distance(p), self.b.distance(p));
+        let h = (0.5 - 0.5 * (d2 + d1) / self.k).clamp(0.0, 1.0);
+        d1 + (-d2 - d1) * h + self.k * h * (1.0 - h)
+    }
+}
+
+// 平移，便于先在原点附近做变形再放到场景中
+pub struct SdfTranslate {
+    inner: Arc<dyn Sdf>,
+    offset: Vec3,
+}
+
+impl SdfTranslate {
+    pub fn new(inner: Arc<dyn Sdf>, offset: Vec3) -> Self {
+        Self { inner, offset }
+    }
+}
+
+impl Sdf for SdfTranslate {
+    fn distance(&self, p: Point3) -> f64 {
+        self.inner.distance(p - self.offset)
+    }
+}
+
+// 绕y轴扭转，每单位高度旋转k弧度。会破坏距离性质，需配合较小的step_scale
+pub struct SdfTwist {
+    inner: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SdfTwist {
+    pub fn new(inner: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { inner, k }
+    }
+}
+
+impl Sdf for SdfTwist {
+    fn distance(&self, p: Point3) -> f64 {
+        let (s, c) = (self.k * p.y()).sin_cos();
+        let q = Point3::new(c * p.x() - s * p.z(), p.y(), s * p.x() + c * p.z());
+        self.inner.distance(q)
+    }
+}
+
+// 沿x方向在xy平面内弯曲
+pub struct SdfBend {
+    inner: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SdfBend {
+    pub fn new(inner: Arc<dyn Sdf>, k: f64) -> Self {
+        Self { inner, k }
+    }
+}
+
+impl Sdf for SdfBend {
+    fn distance(&self, p: Point3) -> f64 {
+        let (s, c) = (self.k * p.x()).sin_cos();
+        let q = Point3::new(c * p.x() - s * p.y(), s * p.x() + c * p.y(), p.z());
+        self.inner.distance(q)
+    }
+}
+
+// 用球面追踪渲染距离函数，包围盒由调用者给出
+pub struct SdfShape {
+    sdf: Arc<dyn Sdf>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    pub max_steps: usize,
+    pub epsilon: f64,
+    pub step_scale: f64, //扭转、弯曲等变形后距离被高估，需要缩小步长
+}
+
+impl SdfShape {
+    pub fn new(sdf: Arc<dyn Sdf>, bbox: Aabb, mat: Arc<dyn Material>) -> Self {
+        Self {
+            sdf,
+            mat,
+            bbox,
+            max_steps: 256,
+            epsilon: 1e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    // 四面体差分求梯度
+    fn gradient(&self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let k0 = Vec3::new(1.0, -1.0, -1.0);
+        let k1 = Vec3::new(-1.0, -1.0, 1.0);
+        let k2 = Vec3::new(-1.0, 1.0, -1.0);
+        let k3 = Vec3::new(1.0, 1.0, 1.0);
+        k0 * self.sdf.distance(p + k0 * h)
+            + k1 * self.sdf.distance(p + k1 * h)
+            + k2 * self.sdf.distance(p + k2 * h)
+            + k3 * self.sdf.distance(p + k3 * h)
+    }
+
+    // 球面步进求第一个交点的t
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<f64> {
+        let mut box_t = ray_t.clone();
+        if !self.bbox.hit(r, &mut box_t) {
+            return None;
+        }
+
+        // 在归一化方向上步进，s为真实距离
+        let len = r.dir.length();
+        let dir = r.dir / len;
+        let s_max = box_t.max * len;
+        let mut s = box_t.min * len;
+
+        // 起点在表面附近时，用梯度判断光线是离开还是进入物体
+        let d0 = self.sdf.distance(r.orig + s * dir);
+        let side = if d0.abs() < self.epsilon {
+            if Vec3::dot(self.gradient(r.orig + s * dir), dir) > 0.0 { 1.0 } else { -1.0 }
+        } else {
+            d0.signum()
+        };
+        s += self.epsilon;
+
+        let mut found = false;
+        for _ in 0..self.max_steps {
+            if s > s_max {
+                break;
+            }
+            let d = side * self.sdf.distance(r.orig + s * dir);
+            if d < self.epsilon {
+                found = true;
+                break;
+            }
+            s += d * self.step_scale;
+        }
+        if !found || !ray_t.surrounds(s / len) {
+            return None;
+        }
+        Some(s / len)
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    // 求梯度要多次计算距离函数，只在最终交点上做
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some(t) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, t);
+        true
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        rec.p = r.at(rec.t);
+        let outward_normal = Vec3::unit_vector(self.gradient(rec.p));
+        rec.set_face_normal(r, outward_normal);
+        let theta = (-outward_normal.y()).acos();
+        let phi = (-outward_normal.z()).atan2(outward_normal.x()) + util::PI;
+        rec.u = phi / (2.0 * util::PI);
+        rec.v = theta / util::PI;
+        rec.set_material(&*self.mat);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec3::Color;
+
+    fn hit(object: &dyn Hittable, orig: Point3, dir: Vec3) -> Option<HitRecord<'_>> {
+        let mut rec = HitRecord::default();
+        object.hit(&Ray::new(orig, dir), &Interval::new(0.001, util::INFINITY), &mut rec).then_some(rec)
+    }
+
+    fn bounds() -> Aabb {
+        Aabb::new_point(&Point3::new(-2.0, -2.0, -2.0), &Point3::new(2.0, 2.0, 2.0))
+    }
+
+    #[test]
+    fn test_matches_analytic_sphere() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let sphere = SdfShape::new(Arc::new(SdfSphere::new(Point3::zero(), 1.0)), bounds(), mat);
+
+        // 与解析解比较，方向不归一化时t也按原方向计
+        let rec = hit(&sphere, Point3::new(-5.0, 0.3, 0.0), Vec3::new(2.0, 0.0, 0.0)).unwrap();
+        let p = Point3::new(-(1.0f64 - 0.09).sqrt(), 0.3, 0.0);
+        assert!((rec.t - (5.0 + p.x()) / 2.0).abs() < 1e-4);
+        assert!((rec.normal - p).length() < 1e-3);
+        assert!(rec.front_face);
+        assert!(sphere.occluded(&Ray::new(Point3::new(-5.0, 0.3, 0.0), Vec3::new(1.0, 0.0, 0.0)), &Interval::new(0.001, util::INFINITY)));
+
+        // 掠过表面外侧不算命中
+        assert!(hit(&sphere, Point3::new(-5.0, 1.01, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_none());
+        assert!(hit(&sphere, Point3::new(-5.0, 0.99, 0.0), Vec3::new(1.0, 0.0, 0.0)).is_some());
+    }
+
+    #[test]
+    fn test_matches_analytic_box() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let cube = SdfShape::new(Arc::new(SdfBox::new(Point3::zero(), Vec3::ones())), bounds(), mat);
+
+        let rec = hit(&cube, Point3::new(0.2, -0.4, 5.0), Vec3::new(0.0, 0.0, -1.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-4);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-3);
+
+        let rec = hit(&cube, Point3::new(0.5, 5.0, 0.5), Vec3::new(0.0, -1.0, 0.0)).unwrap();
+        assert!((rec.t - 4.0).abs() < 1e-4);
+        assert!((rec.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-3);
+
+        // 沿棱外侧掠过
+        assert!(hit(&cube, Point3::new(1.01, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn test_ray_starting_inside() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let sphere = SdfShape::new(Arc::new(SdfSphere::new(Point3::zero(), 1.0)), bounds(), mat.clone());
+        let rec = hit(&sphere, Point3::zero(), Vec3::new(1.0, 0.0, 0.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-4);
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-3);
+
+        let cube = SdfShape::new(Arc::new(SdfBox::new(Point3::zero(), Vec3::ones())), bounds(), mat);
+        let rec = hit(&cube, Point3::new(0.2, 0.3, 0.0), Vec3::new(0.0, 0.0, 1.0)).unwrap();
+        assert!((rec.t - 1.0).abs() < 1e-4);
+        assert!(!rec.front_face);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-3);
+    }
+}
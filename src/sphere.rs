@@ -5,6 +5,8 @@ use crate::ray::Ray;
 use crate::vec3::*;
 use crate::util;
 use crate::aabb::*;
+use crate::csg::{Solid, Span};
+use crate::packet::{RayPacket, MAX_PACKET};
 use std::sync::Arc;
 pub struct Sphere {
     // pub center: Point3,
@@ -44,19 +46,13 @@ impl Sphere {
         }
     }
 
+    // 球心在时刻0到1之间从center1移到center2，之外停在两端，与包围盒一致
     fn sphere_center(&self, time: f64) -> Point3 {
-        self.center1 + self.center_vec * time
+        self.center1 + self.center_vec * time.clamp(0.0, 1.0)
     }
 
-    fn get_sphere_uv(p :Point3) -> (f64,f64) {
-        let theta = (-p.y()).acos();
-        let phi = (-p.z()).atan2(p.x()) + util::PI;
-         
-        (phi / (2.0 * util::PI), theta / util::PI)
-    }
-}
-impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: &Interval, hit_record: &mut HitRecord) -> bool {
+    // 区间内最近的根和光线时刻的球心
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<(f64, Point3)> {
         let center = if self.is_moving {
             self.sphere_center(r.tm) 
         } else { 
@@ -68,26 +64,182 @@ impl Hittable for Sphere {
         let c = oc.squared_length() - self.radius * self.radius;
         let discriminant = h * h - a * c;
         if discriminant < 0.0 {
-            return false;
+            return None;
         }
         let sqrtd = discriminant.sqrt();
         let mut root = (h - sqrtd) / a;
         if !ray_t.surrounds(root) {
             root = (h + sqrtd) / a;
             if !ray_t.surrounds(root) {
-                return false;
+                return None;
             }
         }
+        Some((root, center))
+    }
+
+    fn set_record<'a>(&'a self, r: &Ray, root: f64, center: Point3, hit_record: &mut HitRecord<'a>) {
         hit_record.t = root;
         hit_record.p = r.at(hit_record.t);
-        let outward_normal = (hit_record.p - self.center1) / self.radius;
+        let outward_normal = (hit_record.p - center) / self.radius;
         hit_record.set_face_normal(r, outward_normal);
         (hit_record.u, hit_record.v) = Self::get_sphere_uv(outward_normal);
-        hit_record.mat = Some(Arc::clone(&self.mat));
-        return true;
+        hit_record.set_material(&*self.mat);
+    }
+
+    fn get_sphere_uv(p :Point3) -> (f64,f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + util::PI;
+         
+        (phi / (2.0 * util::PI), theta / util::PI)
+    }
+}
+impl Hittable for Sphere {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, hit_record);
+        if hit {
+            hit_record.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, hit_record: &mut HitRecord<'a>) -> bool {
+        let Some((root, _)) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        hit_record.defer(self, root);
+        true
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, hit_record: &mut HitRecord<'a>) {
+        let center = if self.is_moving { self.sphere_center(r.tm) } else { self.center1 };
+        self.set_record(r, hit_record.t, center, hit_record);
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
     }
 
     fn bounding_box(&self) -> &Aabb {
         &self.bbox
     }
+
+    // 静止的球逐通道解二次方程，只为命中的光线填写交点信息
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        if self.is_moving {
+            for i in 0..packet.len() {
+                let ray_t = packet.interval(i);
+                if packet.active[i] && self.hit(&packet.rays[i], &ray_t, &mut packet.recs[i]) {
+                    packet.record_hit(i);
+                }
+            }
+            return;
+        }
+        let n = packet.len();
+        let mut roots = [util::INFINITY; MAX_PACKET];
+        for (i, root) in roots.iter_mut().enumerate().take(n) {
+            let oc = [
+                self.center1.x() - packet.orig[0][i],
+                self.center1.y() - packet.orig[1][i],
+                self.center1.z() - packet.orig[2][i],
+            ];
+            let d = [packet.dir[0][i], packet.dir[1][i], packet.dir[2][i]];
+            let a = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+            let h = d[0] * oc[0] + d[1] * oc[1] + d[2] * oc[2];
+            let c = oc[0] * oc[0] + oc[1] * oc[1] + oc[2] * oc[2] - self.radius * self.radius;
+            let discriminant = h * h - a * c;
+            let sqrtd = discriminant.max(0.0).sqrt();
+            let (near, far) = ((h - sqrtd) / a, (h + sqrtd) / a);
+            let (t_min, t_max) = (packet.t_min, packet.t_max[i]);
+            *root = if discriminant < 0.0 {
+                util::INFINITY
+            } else if t_min < near && near < t_max {
+                near
+            } else if t_min < far && far < t_max {
+                far
+            } else {
+                util::INFINITY
+            };
+        }
+        for (i, &root) in roots.iter().enumerate().take(n) {
+            if packet.active[i] && root < util::INFINITY {
+                self.set_record(&packet.rays[i], root, self.center1, &mut packet.recs[i]);
+                packet.record_hit(i);
+            }
+        }
+    }
+}
+
+impl Solid for Sphere {
+    fn spans<'a>(&'a self, r: &Ray, out: &mut Vec<Span<'a>>) {
+        let center = if self.is_moving {
+            self.sphere_center(r.tm)
+        } else {
+            self.center1
+        };
+        let oc = center - r.orig;
+        let a = r.dir.squared_length();
+        let h = Vec3::dot(r.dir, oc);
+        let c = oc.squared_length() - self.radius * self.radius;
+        let discriminant = h * h - a * c;
+        if discriminant < 0.0 {
+            return;
+        }
+        let sqrtd = discriminant.sqrt();
+
+        let boundary = |t: f64| {
+            let p = r.at(t);
+            let outward_normal = (p - center) / self.radius;
+            let (u, v) = Self::get_sphere_uv(outward_normal);
+            HitRecord {
+                p,
+                normal: outward_normal,
+                t,
+                front_face: true,
+                mat: Some(&*self.mat),
+                u,
+                v,
+                ..Default::default()
+            }
+        };
+        out.push(Span {
+            enter: boundary((h - sqrtd) / a),
+            exit: boundary((h + sqrtd) / a),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::{BvhNode, FlatBvh};
+    use crate::hittable_list::HittableList;
+    use crate::material::Lambertian;
+
+    #[test]
+    fn test_moving_sphere_after_shutter() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::default();
+        list.add(Arc::new(Sphere::new_center2(Point3::zero(), Point3::new(4.0, 0.0, 0.0), 1.0, mat.clone())));
+        list.add(Arc::new(Sphere::new(Point3::new(-6.0, 0.0, 0.0), 1.0, mat)));
+        let bvh = BvhNode::new(&list);
+        let flat = FlatBvh::new(&list);
+
+        // 时刻1之后球停在终点，BVH与逐个求交结果一致
+        let t = Interval::new(0.001, util::INFINITY);
+        for tm in [1.0, 2.0, 30.0] {
+            let r = Ray::new_time(Point3::new(4.3, 0.4, 10.0), Vec3::new(0.0, 0.0, -1.0), tm);
+            let z = 0.75f64.sqrt();
+            for world in [&list as &dyn Hittable, &bvh, &flat] {
+                let mut rec = HitRecord::default();
+                assert!(world.hit(&r, &t, &mut rec));
+                assert!((rec.t - (10.0 - z)).abs() < 1e-9);
+                assert!((rec.normal - Vec3::new(0.3, 0.4, z)).length() < 1e-9);
+            }
+            let beyond = Ray::new_time(Point3::new(8.0, 0.0, 10.0), Vec3::new(0.0, 0.0, -1.0), tm);
+            assert!(!list.hit(&beyond, &t, &mut HitRecord::default()));
+        }
+        // 时刻0之前停在起点
+        let r = Ray::new_time(Point3::new(0.3, 0.4, 10.0), Vec3::new(0.0, 0.0, -1.0), -3.0);
+        assert!(flat.hit(&r, &t, &mut HitRecord::default()));
+    }
 }
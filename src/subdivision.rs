@@ -0,0 +1,295 @@
+use crate::mesh::TriangleMesh;
+use crate::vec3::{Point3, Vec3};
+use std::collections::BTreeMap;
+
+fn edge_key(a: usize, b: usize) -> (usize, usize) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+// 边表按顶点编号排序，新顶点的编号和邻点的求和顺序都是确定的，同样的输入总得到同样的网格。
+// 每条边记录相邻面（Loop中为对顶点，Catmull-Clark中为面索引）以及新生成的顶点编号
+struct EdgeInfo {
+    adjacent: Vec<usize>,
+    new_index: usize,
+}
+
+// 边界顶点的两个边界邻点，内部顶点返回空
+fn boundary_neighbors(v: usize, neighbors: &[usize], edges: &BTreeMap<(usize, usize), EdgeInfo>) -> Vec<usize> {
+    neighbors
+        .iter()
+        .copied()
+        .filter(|&n| edges[&edge_key(v, n)].adjacent.len() == 1)
+        .collect()
+}
+
+fn vertex_neighbors(vertex_count: usize, edges: &BTreeMap<(usize, usize), EdgeInfo>) -> Vec<Vec<usize>> {
+    let mut neighbors = vec![Vec::new(); vertex_count];
+    for &(a, b) in edges.keys() {
+        neighbors[a].push(b);
+        neighbors[b].push(a);
+    }
+    neighbors
+}
+
+// Loop细分：每次把一个三角形分成4个，levels次后重新计算平滑法线；
+// UV不做平滑，边上的新顶点取两端UV的中点
+pub fn loop_subdivide(mesh: &TriangleMesh, levels: usize) -> TriangleMesh {
+    let mut positions = mesh.positions.clone();
+    let mut uvs = mesh.uvs.clone();
+    let mut indices = mesh.indices.clone();
+
+    for _ in 0..levels {
+        let mut edges: BTreeMap<(usize, usize), EdgeInfo> = BTreeMap::new();
+        for &[a, b, c] in &indices {
+            for (p, q, opposite) in [(a, b, c), (b, c, a), (c, a, b)] {
+                edges
+                    .entry(edge_key(p, q))
+                    .or_insert(EdgeInfo { adjacent: Vec::new(), new_index: 0 })
+                    .adjacent
+                    .push(opposite);
+            }
+        }
+        let neighbors = vertex_neighbors(positions.len(), &edges);
+
+        // 旧顶点的新位置
+        let mut new_positions: Vec<Point3> = (0..positions.len())
+            .map(|v| {
+                let n = neighbors[v].len();
+                if n == 0 {
+                    return positions[v];
+                }
+                let boundary = boundary_neighbors(v, &neighbors[v], &edges);
+                if boundary.len() == 2 {
+                    0.75 * positions[v] + 0.125 * (positions[boundary[0]] + positions[boundary[1]])
+                } else if !boundary.is_empty() {
+                    // 非流形边界保持不动
+                    positions[v]
+                } else {
+                    let beta = if n == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * n as f64) };
+                    let sum = neighbors[v].iter().fold(Vec3::zero(), |acc, &k| acc + positions[k]);
+                    (1.0 - n as f64 * beta) * positions[v] + beta * sum
+                }
+            })
+            .collect();
+
+        // 边上的新顶点
+        for (&(a, b), info) in edges.iter_mut() {
+            let p = if info.adjacent.len() == 2 {
+                0.375 * (positions[a] + positions[b]) + 0.125 * (positions[info.adjacent[0]] + positions[info.adjacent[1]])
+            } else {
+                0.5 * (positions[a] + positions[b])
+            };
+            info.new_index = new_positions.len();
+            new_positions.push(p);
+            if !uvs.is_empty() {
+                uvs.push((0.5 * (uvs[a].0 + uvs[b].0), 0.5 * (uvs[a].1 + uvs[b].1)));
+            }
+        }
+
+        let mut new_indices = Vec::with_capacity(indices.len() * 4);
+        for &[a, b, c] in &indices {
+            let ab = edges[&edge_key(a, b)].new_index;
+            let bc = edges[&edge_key(b, c)].new_index;
+            let ca = edges[&edge_key(c, a)].new_index;
+            new_indices.push([a, ab, ca]);
+            new_indices.push([b, bc, ab]);
+            new_indices.push([c, ca, bc]);
+            new_indices.push([ab, bc, ca]);
+        }
+
+        positions = new_positions;
+        indices = new_indices;
+    }
+
+    let mut result = TriangleMesh::new(positions, indices);
+    result.uvs = uvs;
+    result.compute_smooth_normals();
+    result
+}
+
+// 任意多边形网格，用作Catmull-Clark的输入
+#[derive(Clone, Default)]
+pub struct PolyMesh {
+    pub positions: Vec<Point3>,
+    pub faces: Vec<Vec<usize>>,
+}
+
+impl PolyMesh {
+    pub fn new(positions: Vec<Point3>, faces: Vec<Vec<usize>>) -> Self {
+        Self { positions, faces }
+    }
+
+    pub fn from_triangles(mesh: &TriangleMesh) -> Self {
+        Self::new(mesh.positions.clone(), mesh.indices.iter().map(|t| t.to_vec()).collect())
+    }
+
+    // 扇形三角化，交给三角形管线
+    pub fn triangulate(&self) -> TriangleMesh {
+        let mut indices = Vec::new();
+        for face in &self.faces {
+            for k in 1..face.len().saturating_sub(1) {
+                indices.push([face[0], face[k], face[k + 1]]);
+            }
+        }
+        TriangleMesh::new(self.positions.clone(), indices)
+    }
+
+    // 单次Catmull-Clark细分，结果全部为四边形
+    fn catmull_clark_step(&self) -> Self {
+        let positions = &self.positions;
+
+        let face_points: Vec<Point3> = self
+            .faces
+            .iter()
+            .map(|f| f.iter().fold(Vec3::zero(), |acc, &k| acc + positions[k]) / f.len() as f64)
+            .collect();
+
+        let mut edges: BTreeMap<(usize, usize), EdgeInfo> = BTreeMap::new();
+        for (fi, face) in self.faces.iter().enumerate() {
+            for k in 0..face.len() {
+                edges
+                    .entry(edge_key(face[k], face[(k + 1) % face.len()]))
+                    .or_insert(EdgeInfo { adjacent: Vec::new(), new_index: 0 })
+                    .adjacent
+                    .push(fi);
+            }
+        }
+        let neighbors = vertex_neighbors(positions.len(), &edges);
+
+        let mut vertex_faces = vec![Vec::new(); positions.len()];
+        for (fi, face) in self.faces.iter().enumerate() {
+            for &v in face {
+                vertex_faces[v].push(fi);
+            }
+        }
+
+        // 旧顶点：(F + 2R + (n-3)P) / n，边界上取 3/4 P + 1/8 两个边界邻点
+        let mut new_positions: Vec<Point3> = (0..positions.len())
+            .map(|v| {
+                let n = neighbors[v].len();
+                if n == 0 {
+                    return positions[v];
+                }
+                let boundary = boundary_neighbors(v, &neighbors[v], &edges);
+                if boundary.len() == 2 {
+                    return 0.75 * positions[v] + 0.125 * (positions[boundary[0]] + positions[boundary[1]]);
+                } else if !boundary.is_empty() {
+                    return positions[v];
+                }
+                let f = vertex_faces[v].iter().fold(Vec3::zero(), |acc, &fi| acc + face_points[fi])
+                    / vertex_faces[v].len() as f64;
+                let r = neighbors[v].iter().fold(Vec3::zero(), |acc, &k| acc + 0.5 * (positions[v] + positions[k]))
+                    / n as f64;
+                (f + 2.0 * r + (n as f64 - 3.0) * positions[v]) / n as f64
+            })
+            .collect();
+
+        let face_base = new_positions.len();
+        new_positions.extend_from_slice(&face_points);
+
+        for (&(a, b), info) in edges.iter_mut() {
+            let p = if info.adjacent.len() == 2 {
+                0.25 * (positions[a] + positions[b] + face_points[info.adjacent[0]] + face_points[info.adjacent[1]])
+            } else {
+                0.5 * (positions[a] + positions[b])
+            };
+            info.new_index = new_positions.len();
+            new_positions.push(p);
+        }
+
+        let mut faces = Vec::new();
+        for (fi, face) in self.faces.iter().enumerate() {
+            let m = face.len();
+            for k in 0..m {
+                let prev = face[(k + m - 1) % m];
+                let next = face[(k + 1) % m];
+                faces.push(vec![
+                    face[k],
+                    edges[&edge_key(face[k], next)].new_index,
+                    face_base + fi,
+                    edges[&edge_key(prev, face[k])].new_index,
+                ]);
+            }
+        }
+
+        Self::new(new_positions, faces)
+    }
+
+    pub fn catmull_clark(&self, levels: usize) -> Self {
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            mesh = mesh.catmull_clark_step();
+        }
+        mesh
+    }
+}
+
+// Catmull-Clark细分后三角化并计算平滑法线
+pub fn catmull_clark_subdivide(mesh: &PolyMesh, levels: usize) -> TriangleMesh {
+    let mut result = mesh.catmull_clark(levels).triangulate();
+    result.compute_smooth_normals();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tetrahedron() -> TriangleMesh {
+        TriangleMesh::new(
+            vec![
+                Point3::new(1.0, 1.0, 1.0),
+                Point3::new(1.0, -1.0, -1.0),
+                Point3::new(-1.0, 1.0, -1.0),
+                Point3::new(-1.0, -1.0, 1.0),
+            ],
+            vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+        )
+    }
+
+    #[test]
+    fn test_loop_counts_and_shrink() {
+        let mesh = loop_subdivide(&tetrahedron(), 2);
+        // V' = V + E，F' = 4F
+        assert_eq!(mesh.indices.len(), 4 * 16);
+        assert_eq!(mesh.positions.len(), 4 + 6 + 24);
+        // Loop是逼近型细分，顶点向内收缩
+        assert!(mesh.positions.iter().all(|p| p.length() < 3f64.sqrt()));
+    }
+
+    #[test]
+    fn test_loop_is_deterministic_and_keeps_uvs() {
+        let mut tet = tetrahedron();
+        tet.uvs = vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+        let a = loop_subdivide(&tet, 2);
+        let b = loop_subdivide(&tet, 2);
+        assert_eq!(a.indices, b.indices);
+        assert_eq!(a.positions, b.positions);
+
+        // 每个顶点都有UV，旧顶点保持原值，第一层的边中点取两端的平均
+        assert_eq!(a.uvs.len(), a.positions.len());
+        assert_eq!(&a.uvs[..4], &tet.uvs[..]);
+        assert!(a.uvs[4..10].contains(&(0.5, 0.0)) && a.uvs[4..10].contains(&(0.5, 1.0)));
+        assert!(loop_subdivide(&tetrahedron(), 1).uvs.is_empty());
+    }
+
+    #[test]
+    fn test_catmull_clark_cube() {
+        let positions = (0..8)
+            .map(|k| Point3::new((k & 1) as f64 * 2.0 - 1.0, ((k >> 1) & 1) as f64 * 2.0 - 1.0, ((k >> 2) & 1) as f64 * 2.0 - 1.0))
+            .collect();
+        let faces = vec![
+            vec![0, 2, 3, 1],
+            vec![4, 5, 7, 6],
+            vec![0, 1, 5, 4],
+            vec![2, 6, 7, 3],
+            vec![0, 4, 6, 2],
+            vec![1, 3, 7, 5],
+        ];
+        let cube = PolyMesh::new(positions, faces).catmull_clark(1);
+        assert_eq!(cube.faces.len(), 24);
+        assert_eq!(cube.positions.len(), 8 + 6 + 12);
+        // 立方体角点一次细分后为 (5/9, 5/9, 5/9)
+        assert!((cube.positions[7] - Point3::new(5.0 / 9.0, 5.0 / 9.0, 5.0 / 9.0)).length() < 1e-12);
+    }
+}
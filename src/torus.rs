@@ -0,0 +1,230 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::util;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+pub struct Torus {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Torus {
+    // axis为对称轴，major_radius为管中心圆半径，minor_radius为管半径
+    pub fn new(center: Point3, axis: Vec3, major_radius: f64, minor_radius: f64, mat: Arc<dyn Material>) -> Self {
+        let onb = Onb::new(axis);
+        let rvec = Vec3::new(minor_radius, minor_radius, minor_radius);
+        let e = onb.disk_extent(major_radius) + rvec;
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            onb,
+            mat,
+            bbox: Aabb::new_point(&(center - e), &(center + e)),
+        }
+    }
+
+    fn poly_eval(c: &[f64], x: f64) -> f64 {
+        c.iter().rev().fold(0.0, |acc, &ci| acc * x + ci)
+    }
+
+    // 各项绝对值之和，作为判断多项式值接近0的尺度
+    fn poly_scale(c: &[f64], x: f64) -> f64 {
+        c.iter().rev().fold(0.0, |acc, &ci| acc * x.abs() + ci.abs())
+    }
+
+    // 求 c[0] + c[1]x + ... 在[lo,hi]内的实根，按升序写入roots。
+    // 导数的根把区间切成单调段，每段内变号则二分。
+    // 光线与圆环相切时是重根，两侧不变号，二分找不到，所以临界点上的值接近0时也记为根
+    fn solve_poly(c: &[f64], lo: f64, hi: f64, roots: &mut [f64; 4]) -> usize {
+        let degree = c.len() - 1;
+        if degree == 1 {
+            if c[1] == 0.0 {
+                return 0;
+            }
+            let x = -c[0] / c[1];
+            if (lo..=hi).contains(&x) {
+                roots[0] = x;
+                return 1;
+            }
+            return 0;
+        }
+
+        let mut dc = [0.0; 4];
+        for i in 0..degree {
+            dc[i] = (i + 1) as f64 * c[i + 1];
+        }
+        let mut crit = [0.0; 4];
+        let n_crit = Self::solve_poly(&dc[..degree], lo, hi, &mut crit);
+
+        let mut count = 0;
+        let mut a = lo;
+        let mut fa = Self::poly_eval(c, a);
+        // 相邻的临界点之间单调，逐段检查变号
+        for (k, b) in crit[..n_crit].iter().copied().chain([hi]).enumerate() {
+            let mut fb = Self::poly_eval(c, b);
+            if k < n_crit && fa * fb >= 0.0 && fb.abs() <= 1e-12 * Self::poly_scale(c, b) {
+                // 前一段没有根，临界点处相切；置0使下一段不再重复记录
+                roots[count] = b;
+                count += 1;
+                fb = 0.0;
+            } else if fa * fb < 0.0 {
+                let (mut x0, mut x1, mut f0) = (a, b, fa);
+                for _ in 0..64 {
+                    let mid = 0.5 * (x0 + x1);
+                    let fm = Self::poly_eval(c, mid);
+                    if f0 * fm <= 0.0 {
+                        x1 = mid;
+                    } else {
+                        x0 = mid;
+                        f0 = fm;
+                    }
+                    if x1 - x0 < 1e-10 * (1.0 + x0.abs()) {
+                        break;
+                    }
+                }
+                roots[count] = 0.5 * (x0 + x1);
+                count += 1;
+            }
+            a = b;
+            fa = fb;
+        }
+        count
+    }
+
+    // 最近交点在世界空间中的t
+    fn intersect(&self, r: &Ray, ray_t: &Interval) -> Option<f64> {
+        // 局部坐标系，对称轴为z，方向归一化后求四次方程
+        let len = r.dir.length();
+        let o = self.onb.to_local(r.orig - self.center);
+        let d = self.onb.to_local(r.dir) / len;
+
+        // 先用包围球裁剪出有限的求根区间，略微放大避免根恰好落在端点
+        let bound = (self.major_radius + self.minor_radius) * (1.0 + 1e-6);
+        let f = Vec3::dot(o, d);
+        let disc = f * f - (o.squared_length() - bound * bound);
+        if disc < 0.0 {
+            return None;
+        }
+        let lo = (-f - disc.sqrt()).max(ray_t.min * len);
+        let hi = (-f + disc.sqrt()).min(ray_t.max * len);
+        if lo >= hi {
+            return None;
+        }
+
+        let rr = self.major_radius * self.major_radius;
+        let e = o.squared_length() - rr - self.minor_radius * self.minor_radius;
+        let coeffs = [
+            e * e - 4.0 * rr * (self.minor_radius * self.minor_radius - o.z() * o.z()),
+            4.0 * f * e + 8.0 * rr * o.z() * d.z(),
+            4.0 * f * f + 2.0 * e + 4.0 * rr * d.z() * d.z(),
+            4.0 * f,
+            1.0,
+        ];
+        let mut roots = [0.0; 4];
+        let n = Self::solve_poly(&coeffs, lo, hi, &mut roots);
+
+        roots[..n].iter().map(|&t| t / len).find(|&t| ray_t.surrounds(t))
+    }
+}
+
+impl Hittable for Torus {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let Some(t) = self.intersect(r, ray_t) else {
+            return false;
+        };
+        rec.defer(self, t);
+        true
+    }
+
+    fn surface<'a>(&'a self, r: &Ray, rec: &mut HitRecord<'a>) {
+        rec.p = r.at(rec.t);
+        let p = self.onb.to_local(rec.p - self.center);
+        let rr = self.major_radius * self.major_radius;
+        let s = p.squared_length();
+        let k = s - rr - self.minor_radius * self.minor_radius;
+        let local_normal = Vec3::new(p.x() * k, p.y() * k, p.z() * (s + rr - self.minor_radius * self.minor_radius));
+
+        rec.set_face_normal(r, Vec3::unit_vector(self.onb.to_world(local_normal)));
+        // u绕对称轴，v绕管截面
+        let ring = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        rec.u = (p.y().atan2(p.x()) + util::PI) / (2.0 * util::PI);
+        rec.v = (p.z().atan2(ring - self.major_radius) + util::PI) / (2.0 * util::PI);
+        rec.set_material(&*self.mat);
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        self.intersect(r, ray_t).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::vec3::Color;
+
+    #[test]
+    fn test_hit_outer_and_inner_wall() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let torus = Torus::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 2.0, 0.5, mat);
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        let mut rec = HitRecord::default();
+
+        assert!(torus.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 1.25).abs() < 1e-8);
+        assert!((rec.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-8);
+
+        // 从管内部出发，击中内壁
+        assert!(torus.hit(&r, &Interval::new(1.3, util::INFINITY), &mut rec));
+        assert!((rec.t - 1.75).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_miss_through_hole() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let torus = Torus::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 2.0, 0.5, mat);
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let mut rec = HitRecord::default();
+        assert!(!torus.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+    }
+
+    #[test]
+    fn test_tangent_ray() {
+        // 贴着管顶掠过的光线在x=-2和x=2处相切，都是重根
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let torus = Torus::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 2.0, 0.5, mat);
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        let mut rec = HitRecord::default();
+        assert!(torus.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+        assert!((rec.t - 3.0).abs() < 1e-6);
+        assert!((rec.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-6);
+        assert!(torus.hit(&r, &Interval::new(4.0, util::INFINITY), &mut rec));
+        assert!((rec.t - 7.0).abs() < 1e-6);
+
+        // 稍高一点就错过
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.501), Vec3::new(1.0, 0.0, 0.0));
+        assert!(!torus.hit(&r, &Interval::new(0.001, util::INFINITY), &mut rec));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::aabb::{Aabb, ROBUST_FACTOR};
+use crate::bvh::{BvhSplit, FlatBvh, LinearNode};
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::interval::Interval;
+use crate::packet::RayPacket;
+use crate::ray::Ray;
+use crate::util::INFINITY;
+use std::sync::Arc;
+
+// 默认渲染使用的加速结构，由cargo特性bvh4/bvh8在编译时选择
+#[cfg(feature = "bvh8")]
+pub type DefaultBvh = WideBvh<8>;
+#[cfg(all(feature = "bvh4", not(feature = "bvh8")))]
+pub type DefaultBvh = WideBvh<4>;
+#[cfg(not(any(feature = "bvh4", feature = "bvh8")))]
+pub type DefaultBvh = FlatBvh;
+
+// N路节点，子节点包围盒按分量分开存放（SoA），一次测试所有子节点，循环可被编译器向量化；
+// count大于0时该子节点是叶子，child为第一个物体的下标，否则child为子节点的下标。
+// 空位的包围盒为空（min为+∞、max为-∞），测试总是失败
+struct WideNode<const N: usize> {
+    min: [[f64; N]; 3],
+    max: [[f64; N]; 3],
+    child: [u32; N],
+    count: [u32; N],
+}
+
+impl<const N: usize> WideNode<N> {
+    fn empty() -> Self {
+        Self {
+            min: [[INFINITY; N]; 3],
+            max: [[-INFINITY; N]; 3],
+            child: [u32::MAX; N],
+            count: [0; N],
+        }
+    }
+
+    // 返回每个子节点的进入距离，未命中为+∞；与Aabb::hit相同的稳健slab测试
+    fn hit_children(&self, r: &Ray, ray_t: &Interval) -> [f64; N] {
+        let inv_dir = r.inv_dir();
+        let mut t_near = [ray_t.min; N];
+        let mut t_far = [ray_t.max; N];
+        for a in 0..3 {
+            let (near, far) = if inv_dir[a] >= 0.0 { (&self.min[a], &self.max[a]) } else { (&self.max[a], &self.min[a]) };
+            let (orig, inv) = (r.orig[a], inv_dir[a]);
+            for i in 0..N {
+                let t0 = (near[i] - orig) * inv;
+                let t1 = (far[i] - orig) * inv * ROBUST_FACTOR;
+                t_near[i] = if t0 > t_near[i] { t0 } else { t_near[i] };
+                t_far[i] = if t1 < t_far[i] { t1 } else { t_far[i] };
+            }
+        }
+        std::array::from_fn(|i| if t_near[i] <= t_far[i] { t_near[i] } else { INFINITY })
+    }
+
+    // 与FlatBvh相同的首次命中遍历：从first开始逐条测试活动光线，记下每个子节点第一条命中它的光线
+    // 及其进入距离，所有非空子节点都找到后即停止
+    fn first_hits(&self, packet: &RayPacket, first: usize) -> [(usize, f64); N] {
+        let mut result = [(usize::MAX, INFINITY); N];
+        let mut remaining = self.child.iter().filter(|&&c| c != u32::MAX).count();
+        for i in first..packet.len() {
+            if remaining == 0 {
+                break;
+            }
+            if !packet.active[i] {
+                continue;
+            }
+            for (k, &t) in self.hit_children(&packet.rays[i], &packet.interval(i)).iter().enumerate() {
+                if t != INFINITY && result[k].0 == usize::MAX {
+                    result[k] = (i, t);
+                    remaining -= 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+// 栈上一项：子节点或叶子，以及光线进入它的距离
+#[derive(Clone, Copy, Default)]
+struct Entry {
+    child: u32,
+    count: u32,
+    t: f64,
+}
+
+const LOCAL_STACK: usize = 64;
+
+// 遍历栈：每次求交都要新建，固定数组保持很小以减少初始化开销，极少数很深的树溢出到Vec
+struct TraversalStack {
+    local: [Entry; LOCAL_STACK],
+    len: usize,
+    spill: Vec<Entry>,
+}
+
+impl TraversalStack {
+    fn new() -> Self {
+        Self { local: [Entry::default(); LOCAL_STACK], len: 0, spill: Vec::new() }
+    }
+
+    fn push(&mut self, entry: Entry) {
+        if self.len < LOCAL_STACK {
+            self.local[self.len] = entry;
+            self.len += 1;
+        } else {
+            self.spill.push(entry);
+        }
+    }
+
+    fn pop(&mut self) -> Option<Entry> {
+        if let Some(entry) = self.spill.pop() {
+            return Some(entry);
+        }
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.local[self.len])
+    }
+}
+
+// 多路BVH：由二叉的FlatBvh折叠而成，每次把面积最大的内部子节点展开，直到凑满N个子节点
+pub struct WideBvh<const N: usize> {
+    nodes: Vec<WideNode<N>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl<const N: usize> WideBvh<N> {
+    pub fn new(list: &HittableList) -> Self {
+        Self::from_objects(list.objects.clone(), BvhSplit::default())
+    }
+
+    pub fn from_objects(objects: Vec<Arc<dyn Hittable>>, split: BvhSplit) -> Self {
+        Self::from_flat(FlatBvh::from_objects(objects, split))
+    }
+
+    pub fn from_flat(flat: FlatBvh) -> Self {
+        assert!((2..=8).contains(&N), "WideBvh supports 2 to 8 children per node");
+        let bbox = flat.nodes[0].bbox.clone();
+        let mut nodes = Vec::with_capacity(flat.nodes.len() / (N - 1) + 1);
+        Self::collapse(&flat.nodes, 0, &mut nodes);
+        Self { nodes, objects: flat.objects, bbox }
+    }
+
+    fn collapse(flat: &[LinearNode], index: usize, nodes: &mut Vec<WideNode<N>>) -> u32 {
+        let children_of = |i: usize| [i + 1, flat[i].offset as usize];
+        let mut children = if flat[index].count > 0 { vec![index] } else { children_of(index).to_vec() };
+        while children.len() < N {
+            let largest = children
+                .iter()
+                .enumerate()
+                .filter(|(_, &c)| flat[c].count == 0)
+                .max_by(|(_, &a), (_, &b)| flat[a].bbox.surface_area().total_cmp(&flat[b].bbox.surface_area()));
+            let k = match largest {
+                Some((k, _)) => k,
+                None => break,
+            };
+            let c = children.swap_remove(k);
+            children.extend(children_of(c));
+        }
+
+        let wide_index = nodes.len();
+        nodes.push(WideNode::empty());
+        for (k, &c) in children.iter().enumerate() {
+            let (child, count) = if flat[c].count > 0 {
+                (flat[c].offset, flat[c].count)
+            } else {
+                (Self::collapse(flat, c, nodes), 0)
+            };
+            let node = &mut nodes[wide_index];
+            for a in 0..3 {
+                node.min[a][k] = flat[c].bbox.axis(a).min;
+                node.max[a][k] = flat[c].bbox.axis(a).max;
+            }
+            node.child[k] = child;
+            node.count[k] = count;
+        }
+        wide_index as u32
+    }
+}
+
+impl<const N: usize> Hittable for WideBvh<N> {
+    fn hit<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let hit = self.hit_deferred(r, ray_t, rec);
+        if hit {
+            rec.finish(r);
+        }
+        hit
+    }
+
+    fn hit_deferred<'a>(&'a self, r: &Ray, ray_t: &Interval, rec: &mut HitRecord<'a>) -> bool {
+        let mut closest = ray_t.clone();
+        let mut hit_anything = false;
+
+        let mut stack = TraversalStack::new();
+        stack.push(Entry { child: 0, count: 0, t: ray_t.min });
+        while let Some(entry) = stack.pop() {
+            if entry.t > closest.max {
+                continue;
+            }
+            if entry.count > 0 {
+                let start = entry.child as usize;
+                for object in &self.objects[start..start + entry.count as usize] {
+                    if object.hit_deferred(r, &closest, rec) {
+                        hit_anything = true;
+                        closest.max = rec.t;
+                    }
+                }
+                continue;
+            }
+
+            let node = &self.nodes[entry.child as usize];
+            let t = node.hit_children(r, &closest);
+            // 命中的子节点按距离从远到近排好再入栈，近的先出栈
+            let mut hits = [Entry::default(); N];
+            let mut n = 0;
+            for (i, &ti) in t.iter().enumerate() {
+                if ti == INFINITY {
+                    continue;
+                }
+                let mut j = n;
+                while j > 0 && hits[j - 1].t < ti {
+                    hits[j] = hits[j - 1];
+                    j -= 1;
+                }
+                hits[j] = Entry { child: node.child[i], count: node.count[i], t: ti };
+                n += 1;
+            }
+            for &hit in &hits[..n] {
+                stack.push(hit);
+            }
+        }
+        hit_anything
+    }
+
+    // 每个子节点带着第一条命中它的光线下标入栈，到叶子时之前的光线都已错过，
+    // 从active中去掉，其余交给图元逐通道求交
+    fn hit_packet<'a>(&'a self, packet: &mut RayPacket<'a>) {
+        let active = packet.active;
+        let Some(first) = packet.first_hit(&self.bbox, 0) else {
+            return;
+        };
+        let mut stack = vec![(Entry { child: 0, count: 0, t: 0.0 }, first)];
+        while let Some((entry, first)) = stack.pop() {
+            if entry.count > 0 {
+                let start = entry.child as usize;
+                packet.active[..first].fill(false);
+                for object in &self.objects[start..start + entry.count as usize] {
+                    object.hit_packet(packet);
+                }
+                packet.active = active;
+                continue;
+            }
+
+            let node = &self.nodes[entry.child as usize];
+            // 命中的子节点按进入距离从远到近入栈，近的先出栈
+            let mut hits: Vec<_> = node
+                .first_hits(packet, first)
+                .iter()
+                .enumerate()
+                .filter(|(_, &(i, _))| i != usize::MAX)
+                .map(|(k, &(i, t))| (Entry { child: node.child[k], count: node.count[k], t }, i))
+                .collect();
+            hits.sort_by(|a, b| b.0.t.total_cmp(&a.0.t));
+            stack.extend(hits);
+        }
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bbox
+    }
+
+    fn occluded(&self, r: &Ray, ray_t: &Interval) -> bool {
+        let mut stack = TraversalStack::new();
+        stack.push(Entry { child: 0, count: 0, t: ray_t.min });
+        while let Some(entry) = stack.pop() {
+            if entry.count > 0 {
+                let start = entry.child as usize;
+                if self.objects[start..start + entry.count as usize].iter().any(|object| object.occluded(r, ray_t)) {
+                    return true;
+                }
+                continue;
+            }
+            let node = &self.nodes[entry.child as usize];
+            for (i, &t) in node.hit_children(r, ray_t).iter().enumerate() {
+                if t != INFINITY {
+                    stack.push(Entry { child: node.child[i], count: node.count[i], t });
+                }
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::sphere::Sphere;
+    use crate::util::random_double_range;
+    use crate::vec3::{Color, Point3, Vec3};
+
+    #[test]
+    fn test_wide_matches_brute_force() {
+        let mat = Arc::new(Lambertian::new(Color::ones()));
+        let mut list = HittableList::default();
+        for _ in 0..500 {
+            let center = Point3::random_range(-10.0, 10.0);
+            list.add(Arc::new(Sphere::new(center, random_double_range(0.05, 0.5), mat.clone())));
+        }
+        let wide4 = WideBvh::<4>::new(&list);
+        let wide8 = WideBvh::<8>::new(&list);
+
+        for _ in 0..500 {
+            let r = Ray::new(Point3::random_range(-12.0, 12.0), Vec3::random_range(-1.0, 1.0));
+            let t = Interval::new(0.001, INFINITY);
+            let (mut a, mut b, mut c) = (HitRecord::default(), HitRecord::default(), HitRecord::default());
+            let hit = list.hit(&r, &t, &mut a);
+            assert_eq!(hit, wide4.hit(&r, &t, &mut b));
+            assert_eq!(hit, wide8.hit(&r, &t, &mut c));
+            assert_eq!((a.t, a.t), (b.t, c.t));
+            assert_eq!(hit, wide4.occluded(&r, &t));
+            assert_eq!(hit, wide8.occluded(&r, &t));
+        }
+
+        // 超出固定数组的部分溢出到Vec，仍按后进先出弹出
+        let mut stack = TraversalStack::new();
+        for k in 0..100 {
+            stack.push(Entry { child: k, ..Default::default() });
+        }
+        assert!((0..100).rev().all(|k| stack.pop().unwrap().child == k));
+        assert!(stack.pop().is_none());
+    }
+}